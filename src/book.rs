@@ -0,0 +1,409 @@
+use super::{HeuristicGameTree, Outcome};
+use crate::minimax::{side_key, RootPolicy, SearchConfig, Searcher};
+use crate::rng::Rng;
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+// The first bytes of an opening book file
+const MAGIC: &[u8; 4] = b"HGOB";
+
+/// An opening book: for the positions early in a game, the moves worth playing there, each with
+/// a weight. Probing the book picks one of the moves at random, the more often the heavier it
+/// is, so the computer plays its openings instantly and does not play the same line every game.
+///
+/// Positions are told apart by `HeuristicGameTree::position_key` and the player to move, like
+/// the transposition table does, so the game has to provide keys. A move is stored as where it
+/// comes in the position's `possible_moves`, so a book works for any type of move, and can be
+/// saved to a file and loaded back (see `save` and `load`). A `BookBuilder` fills the book with
+/// deeper searches than the game can afford while it is being played.
+///
+/// # Example
+/// Countdown is the game of the `Searcher` example (see `minimax::Searcher`).
+/// ```
+/// # use heuristic_game_tree::{HeuristicGameTree, Outcome, Player};
+/// # use heuristic_game_tree::book::OpeningBook;
+/// # use heuristic_game_tree::rng::Rng;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/doc/countdown.rs"));
+/// # fn main() {
+/// let mut book = OpeningBook::new();
+/// book.add(&Countdown(10, Player::Max), &1, 1);
+/// assert_eq!(book.probe(&Countdown(10, Player::Max), &mut Rng::new(7)), Some(1));
+/// assert_eq!(book.probe(&Countdown(10, Player::Min), &mut Rng::new(7)), None);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpeningBook {
+    // The index in possible_moves and the weight of every move of a position, by key
+    positions: BTreeMap<u64, Vec<(u16, u32)>>,
+}
+
+impl OpeningBook {
+    /// Creates an empty book.
+    pub fn new() -> Self {
+        OpeningBook::default()
+    }
+
+    /// Adds weight to a move of a position, putting the move in the book if it is not there yet.
+    /// A position without a position key, or a move the position does not have, is left out,
+    /// and so is a move that comes after the first 65536 of `possible_moves`.
+    pub fn add<G: HeuristicGameTree>(&mut self, game: &G, next_move: &G::Move, weight: u32) {
+        let index = game
+            .possible_moves()
+            .position(|m| m == *next_move)
+            .and_then(|index| u16::try_from(index).ok());
+        if let (Some(key), Some(index)) = (side_key(game), index) {
+            let moves = self.positions.entry(key).or_default();
+            match moves.iter_mut().find(|entry| entry.0 == index) {
+                Some(entry) => entry.1 = entry.1.saturating_add(weight),
+                None => moves.push((index, weight)),
+            }
+        }
+    }
+
+    /// Returns the book moves of a position with their weights, in the order of `possible_moves`.
+    pub fn moves<G: HeuristicGameTree>(&self, game: &G) -> Vec<(G::Move, u32)> {
+        let entries = match side_key(game).and_then(|key| self.positions.get(&key)) {
+            Some(entries) => entries,
+            None => return Vec::new(),
+        };
+        game.possible_moves()
+            .enumerate()
+            .filter_map(|(index, m)| {
+                let weight = entries.iter().find(|entry| entry.0 as usize == index)?.1;
+                (weight > 0).then_some((m, weight))
+            })
+            .collect()
+    }
+
+    /// Returns a book move of the position, picked by `rng` with a chance in proportion to its
+    /// weight, or None if the position is not in the book and has to be searched.
+    pub fn probe<G: HeuristicGameTree>(&self, game: &G, rng: &mut Rng) -> Option<G::Move> {
+        let mut moves = self.moves(game);
+        let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = (rng.next_f64() * total as f64) as u64;
+        let index = moves
+            .iter()
+            .position(|(_, weight)| match pick.checked_sub(*weight as u64) {
+                Some(rest) => {
+                    pick = rest;
+                    false
+                }
+                None => true,
+            })
+            .unwrap_or(moves.len() - 1);
+        Some(moves.swap_remove(index).0)
+    }
+
+    /// Returns the number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns whether the book has no positions.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Writes the book: the bytes "HGOB", the number of positions as 8 bytes, and then every
+    /// position as its key in 8 bytes and its number of moves in 2, followed by every move as
+    /// its index in `possible_moves` in 2 bytes and its weight in 4, all little-endian.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.positions.len() as u64).to_le_bytes())?;
+        for (key, moves) in &self.positions {
+            writer.write_all(&key.to_le_bytes())?;
+            writer.write_all(&(moves.len() as u16).to_le_bytes())?;
+            for (index, weight) in moves {
+                writer.write_all(&index.to_le_bytes())?;
+                writer.write_all(&weight.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Reads a book written by `write`.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<OpeningBook> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an opening book",
+            ));
+        }
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count);
+        let mut positions = BTreeMap::new();
+        let mut header = [0; 10];
+        let mut entry = [0; 6];
+        for _ in 0..count {
+            reader.read_exact(&mut header)?;
+            let key = u64::from_le_bytes(header[..8].try_into().unwrap());
+            let moves = u16::from_le_bytes(header[8..].try_into().unwrap());
+            let mut entries = Vec::new();
+            for _ in 0..moves {
+                reader.read_exact(&mut entry)?;
+                let index = u16::from_le_bytes(entry[..2].try_into().unwrap());
+                let weight = u32::from_le_bytes(entry[2..].try_into().unwrap());
+                entries.push((index, weight));
+            }
+            if positions.insert(key, entries).is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "opening book position repeated",
+                ));
+            }
+        }
+        Ok(OpeningBook { positions })
+    }
+
+    /// Saves the book to a file, see `write`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Loads a book saved with `save`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<OpeningBook> {
+        OpeningBook::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Fills an opening book with the moves of deep minimax searches, from games the builder plays
+/// against itself or from records of games played before.
+///
+/// Every move it puts in the book adds 1 to the move's weight, so the moves the searches find
+/// most often are the ones the book plays most often. The searches pick at random between
+/// moves that are equally good, which is what makes the games of `self_play` differ.
+///
+/// # Example
+/// Countdown is the game of the `Searcher` example (see `minimax::Searcher`).
+/// ```
+/// # use heuristic_game_tree::{HeuristicGameTree, Outcome, Player};
+/// # use heuristic_game_tree::book::BookBuilder;
+/// # use heuristic_game_tree::rng::Rng;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/doc/countdown.rs"));
+/// # fn main() {
+/// let mut builder = BookBuilder::new(2, 10).with_rng(Rng::new(3));
+/// builder.self_play(&Countdown(10, Player::Max), 4);
+/// let book = builder.build();
+/// // The first move leaves the other player a multiple of three
+/// assert_eq!(book.moves(&Countdown(10, Player::Max)), vec![(1, 4)]);
+/// assert_eq!(book.len(), 2);
+/// # }
+/// ```
+pub struct BookBuilder<G: HeuristicGameTree> {
+    searcher: Searcher<G>,
+    plies: usize,
+    depth: usize,
+    book: OpeningBook,
+}
+
+impl<G: HeuristicGameTree> BookBuilder<G> {
+    /// Creates a builder with an empty book, which puts the first `plies` moves of every game
+    /// in it, each found by a search `depth` plies deep.
+    pub fn new(plies: usize, depth: usize) -> Self {
+        let config = SearchConfig {
+            policy: RootPolicy::RandomBest,
+            ..SearchConfig::default()
+        };
+        BookBuilder {
+            searcher: Searcher::new().with_config(config),
+            plies,
+            depth,
+            book: OpeningBook::new(),
+        }
+    }
+
+    /// Sets the random number generator the searches pick between equally good moves with.
+    pub fn with_rng(mut self, rng: Rng) -> Self {
+        self.searcher = self.searcher.with_rng(rng);
+        self
+    }
+
+    /// Adds to a book that was built before, instead of starting from an empty one.
+    pub fn with_book(mut self, book: OpeningBook) -> Self {
+        self.book = book;
+        self
+    }
+
+    /// Plays `games` games against itself from the start position, and puts the move it
+    /// finds in every position of the first plies of each game in the book.
+    pub fn self_play(&mut self, start: &G, games: usize) {
+        for _ in 0..games {
+            let mut game = start.clone();
+            for _ in 0..self.plies {
+                if game.outcome() != Outcome::Ongoing {
+                    break;
+                }
+                let next_move = match self.searcher.search(&game, self.depth) {
+                    Some(next_move) => next_move,
+                    None => break,
+                };
+                self.book.add(&game, &next_move, 1);
+                game.execute_move(&next_move);
+            }
+        }
+    }
+
+    /// Puts the first plies of a game record in the book. Every move of the record is checked
+    /// by a search of all the moves of its position: it goes in the book if it scores as well
+    /// as the best move, and the best move goes in instead if it does not. The record is
+    /// followed either way, and stops being followed at a move the position does not have.
+    pub fn add_record(&mut self, start: &G, moves: &[G::Move]) {
+        let mut game = start.clone();
+        for next_move in moves.iter().take(self.plies) {
+            if game.outcome() != Outcome::Ongoing || !game.possible_moves().any(|m| m == *next_move)
+            {
+                break;
+            }
+            let result = self
+                .searcher
+                .analyze_multi_pv(&game, self.depth, usize::MAX);
+            let best = result.ranked_moves.iter().map(|ranked| ranked.score).max();
+            let recorded = result
+                .ranked_moves
+                .iter()
+                .find(|ranked| ranked.next_move == *next_move);
+            match (recorded, result.best_move) {
+                (Some(recorded), _) if Some(recorded.score) == best => {
+                    self.book.add(&game, next_move, 1)
+                }
+                (_, Some(best_move)) => self.book.add(&game, &best_move, 1),
+                _ => {}
+            }
+            game.execute_move(next_move);
+        }
+    }
+
+    /// Returns the book built so far.
+    pub fn build(self) -> OpeningBook {
+        self.book
+    }
+}
+
+#[cfg(test)]
+mod book_tests {
+    use super::{BookBuilder, OpeningBook};
+    use crate::rng::Rng;
+    use crate::test_games::{nim, Nim};
+    use crate::{HeuristicGameTree, Outcome, Player};
+
+    // A position with more moves than a book entry can count
+    #[derive(Clone)]
+    struct Wide;
+
+    impl HeuristicGameTree for Wide {
+        type Move = usize;
+        type Score = isize;
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            Box::new(0..70_000)
+        }
+        fn heuristic(&self) -> isize {
+            0
+        }
+        fn execute_move(&mut self, _next_move: &Self::Move) {}
+        fn to_move(&self) -> Player {
+            Player::Max
+        }
+        fn outcome(&self) -> Outcome {
+            Outcome::Ongoing
+        }
+        fn position_key(&self) -> Option<u64> {
+            Some(0)
+        }
+    }
+
+    #[test]
+    fn probe_test() {
+        let game = nim(10);
+        let mut book = OpeningBook::new();
+        book.add(&game, &1, 1);
+        book.add(&game, &3, 2);
+        book.add(&game, &3, 1);
+        // Not a move of the position
+        book.add(&nim(2), &3, 5);
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.moves(&game), vec![(1, 1), (3, 3)]);
+        let mut rng = Rng::new(11);
+        let threes = (0..1000)
+            .filter(|_| book.probe(&game, &mut rng) == Some(3))
+            .count();
+        assert!((650..850).contains(&threes), "{}", threes);
+        assert_eq!(book.probe(&nim(2), &mut rng), None);
+    }
+
+    #[test]
+    fn wide_test() {
+        let mut book = OpeningBook::new();
+        book.add(&Wide, &69_999, 1);
+        assert!(book.is_empty());
+        book.add(&Wide, &65_535, 1);
+        assert_eq!(book.moves(&Wide), vec![(65_535, 1)]);
+    }
+
+    #[test]
+    fn file_test() {
+        let mut book = OpeningBook::new();
+        book.add(&nim(10), &2, 4);
+        book.add(&nim(9), &1, 1);
+        book.add(&nim(9), &3, 7);
+        let mut bytes = Vec::new();
+        book.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 4 + 8 + 2 * 10 + 3 * 6);
+        assert_eq!(OpeningBook::read(&bytes[..]).unwrap(), book);
+        assert!(OpeningBook::read(&bytes[..bytes.len() - 1]).is_err());
+        bytes[0] = b'X';
+        assert!(OpeningBook::read(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn self_play_test() {
+        let mut builder = BookBuilder::new(3, 12).with_rng(Rng::new(5));
+        builder.self_play(&nim(10), 5);
+        let book = builder.build();
+        // Taking 2 leaves a multiple of four, the only winning move, and the loser's replies vary
+        assert_eq!(book.moves(&nim(10)), vec![(2, 5)]);
+        let lost = Nim {
+            pile: 8,
+            to_move: Player::Min,
+        };
+        assert_eq!(
+            book.moves(&lost)
+                .iter()
+                .map(|(_, weight)| weight)
+                .sum::<u32>(),
+            5
+        );
+        assert!(book.moves(&lost).len() > 1);
+        // Past the first three plies
+        assert!(book
+            .moves(&Nim {
+                pile: 4,
+                to_move: Player::Min
+            })
+            .is_empty());
+    }
+
+    #[test]
+    fn record_test() {
+        let mut builder = BookBuilder::new(2, 12).with_rng(Rng::new(5));
+        // Taking 1 from 10 loses, and so does taking 3 from 9, so the book has the winning
+        // moves instead, and the record is followed to the position after the first move
+        builder.add_record(&nim(10), &[1, 3, 1]);
+        let book = builder.build();
+        assert_eq!(book.moves(&nim(10)), vec![(2, 1)]);
+        let reply = Nim {
+            pile: 9,
+            to_move: Player::Min,
+        };
+        assert_eq!(book.moves(&reply), vec![(1, 1)]);
+        assert_eq!(book.len(), 2);
+    }
+}
@@ -1,18 +1,60 @@
 use super::*;
+use crate::book::{BookBuilder, OpeningBook};
+use crate::general_game::computer_searcher;
+use crate::general_game::think_time;
+use crate::general_game::Engine;
+use crate::general_game::{book_move, BOOK_PLIES};
+use crate::general_game::{node_budget, MAX_DEPTH};
+use crate::mcts::Mcts;
 use crate::minimax;
+use crate::rng::Rng;
+use crate::zobrist::ZobristTable;
 use std::io::{stdout, Write,};
 
 // Could be adjusted by user, but must be even for our algos
 const BOARDWIDTH: usize = 8;
 const BOARDSIZE: usize = BOARDWIDTH * BOARDWIDTH / 2;
 
-#[derive(Clone)]
+// Kinds 0 to 3 are the pieces, the last kind marks the piece that is in the middle of a multi-jump
+const JUMPING: usize = 4;
+static ZOBRIST: ZobristTable<BOARDSIZE, 5> = ZobristTable::new(0x6368_6563_6b65_7273);
+
 struct CheckersGame {
     board: [Option<Piece>; BOARDSIZE],
     o_won: Option<bool>,
     is_o_turn: bool,
     last_skip: Option<usize>,
+    hash: u64,
+    // Every move executed on this copy of the game, see Clone. Nothing clears it, so the game
+    // of a session keeps a few bytes for every move played in the session.
+    undo_history: Vec<Undo>,
+}
+
+// A copy starts without undo history, so that the search does not copy the moves of the whole
+// session along with every position it clones. Moves can only be undone on the copy they were
+// executed on, and the search only undoes the moves it executed on its own copy.
+impl Clone for CheckersGame {
+    fn clone(&self) -> Self {
+        CheckersGame {
+            board: self.board.clone(),
+            o_won: self.o_won,
+            is_o_turn: self.is_o_turn,
+            last_skip: self.last_skip,
+            hash: self.hash,
+            undo_history: Vec::new(),
+        }
+    }
 }
+
+// What a move changed besides moving the piece, so that it can be taken back.
+// The turn was handed over exactly when the move left last_skip empty.
+#[derive(Clone)]
+struct Undo {
+    captured: Option<Piece>,
+    crowned: bool,
+    last_skip: Option<usize>,
+}
+
 #[derive(Clone)]
 pub enum Piece {
     X,
@@ -23,20 +65,21 @@ pub enum Piece {
 
 impl Piece {
     pub fn is_o(&self) -> bool {
-        match &self {
-            Piece::O => true,
-            Piece::OKing => true,
-            _ => false,
-        }
+        matches!(self, Piece::O | Piece::OKing)
     }
     pub fn is_x(&self) -> bool {
         !self.is_o()
     }
     pub fn is_king(&self) -> bool {
-        match &self {
-            Piece::OKing => true,
-            Piece::XKing => true,
-            _ => false,
+        matches!(self, Piece::OKing | Piece::XKing)
+    }
+    /// A number for each kind of piece, used to look up hashing keys
+    pub fn index(&self) -> usize {
+        match self {
+            Piece::X => 0,
+            Piece::O => 1,
+            Piece::XKing => 2,
+            Piece::OKing => 3,
         }
     }
 }
@@ -53,9 +96,9 @@ pub fn print_piece<'a>(item: &Option<Piece>) -> &'a str {
     }
 }
 
-impl<'a> CheckersGame {
+impl CheckersGame {
     pub fn new() -> Self {
-        CheckersGame {
+        let mut game = CheckersGame {
             board: [
                 Some(Piece::X),
                 Some(Piece::X),
@@ -93,7 +136,52 @@ impl<'a> CheckersGame {
             o_won: None,
             is_o_turn: true,
             last_skip: None,
+            hash: 0,
+            undo_history: Vec::new(),
+        };
+        game.hash = game.full_hash();
+        game
+    }
+
+    // Hash of the whole position, only needed once since moves keep the hash up to date
+    fn full_hash(&self) -> u64 {
+        let pieces = self
+            .board
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| p.as_ref().map(|p| (i, p.index())));
+        let mut hash = ZOBRIST.hash_pieces(pieces);
+        if !self.is_o_turn {
+            hash ^= ZOBRIST.side();
+        }
+        if let Some(p) = self.last_skip {
+            hash ^= ZOBRIST.piece(p, JUMPING);
         }
+        hash
+    }
+
+    // Every change to the board goes through here so that the hash stays up to date
+    fn set_square(&mut self, pos: usize, piece: Option<Piece>) {
+        if let Some(old) = &self.board[pos] {
+            self.hash ^= ZOBRIST.piece(pos, old.index());
+        }
+        if let Some(new) = &piece {
+            self.hash ^= ZOBRIST.piece(pos, new.index());
+        }
+        self.board[pos] = piece;
+    }
+    fn set_last_skip(&mut self, last_skip: Option<usize>) {
+        if let Some(p) = self.last_skip {
+            self.hash ^= ZOBRIST.piece(p, JUMPING);
+        }
+        if let Some(p) = last_skip {
+            self.hash ^= ZOBRIST.piece(p, JUMPING);
+        }
+        self.last_skip = last_skip;
+    }
+    fn switch_turn(&mut self) {
+        self.is_o_turn = !self.is_o_turn;
+        self.hash ^= ZOBRIST.side();
     }
 
     pub fn print_board<W: Write>(&self, output: &mut W) -> std::io::Result<()> {
@@ -222,9 +310,9 @@ impl<'a> CheckersGame {
     }
     pub fn maybe_make_king(&mut self, pos: usize) {
         if pos < BOARDWIDTH / 2 && self.board[pos].as_ref().unwrap().is_o() {
-            self.board[pos] = Some(Piece::OKing);
+            self.set_square(pos, Some(Piece::OKing));
         } else if pos >= BOARDSIZE - BOARDWIDTH / 2 && self.board[pos].as_ref().unwrap().is_x() {
-            self.board[pos] = Some(Piece::XKing);
+            self.set_square(pos, Some(Piece::XKing));
         }
     }
     pub fn is_o_winner(&self) -> Option<bool> {
@@ -258,14 +346,15 @@ impl<'a> CheckersGame {
         !self.is_o_turn
     }
     pub fn give_up_turn(&mut self) {
-        self.is_o_turn = !self.is_o_turn;
+        self.switch_turn();
     }
 }
 
-impl<'a> HeuristicGameTree for CheckersGame {
+impl HeuristicGameTree for CheckersGame {
     type Move = (usize, usize, Option<usize>);
+    type Score = isize;
 
-    fn heuristic(&self) -> isize {
+    fn heuristic(&self) -> Self::Score {
         let mut h: isize = 0;
         for i in 0..BOARDSIZE {
             if let Some(p) = &self.board[i] {
@@ -278,30 +367,92 @@ impl<'a> HeuristicGameTree for CheckersGame {
         }
         h
     }
-    fn execute_move(&mut self, m: &Self::Move, _is_opponent: bool) -> bool {
-        self.last_skip = None;
+    fn execute_move(&mut self, m: &Self::Move) {
+        let was_king = self.board[m.0].as_ref().is_some_and(|p| p.is_king());
+        let mut undo = Undo {
+            captured: m.2.and_then(|jumped| self.board[jumped].clone()),
+            crowned: false,
+            last_skip: self.last_skip,
+        };
+        self.set_last_skip(None);
         if let Some(jumped) = m.2 {
-            self.board[jumped] = None;
-            self.board[m.1] = self.board[m.0].clone();
-            self.board[m.0] = None;
+            self.set_square(jumped, None);
+            self.set_square(m.1, self.board[m.0].clone());
+            self.set_square(m.0, None);
             self.maybe_make_king(m.1);
             if self.possible_positions_jump(m.1).is_empty() {
                 // No jumps left from this piece, give over turn
-                self.is_o_turn = !self.is_o_turn;
+                self.switch_turn();
             } else {
                 // Another jump available, assign so that it only
                 // gives possible moves of this jump
-                self.last_skip = Some(m.1);
+                self.set_last_skip(Some(m.1));
             }
         } else {
-            self.board[m.1] = self.board[m.0].clone();
-            self.board[m.0] = None;
+            self.set_square(m.1, self.board[m.0].clone());
+            self.set_square(m.0, None);
             self.maybe_make_king(m.1);
-            self.is_o_turn = !self.is_o_turn; // Next player's move
+            self.switch_turn(); // Next player's move
+        }
+        undo.crowned = !was_king && self.board[m.1].as_ref().is_some_and(|p| p.is_king());
+        self.undo_history.push(undo);
+    }
+    fn is_noisy(&self, m: &Self::Move) -> bool {
+        // Captures, including the rest of a multi-jump
+        m.2.is_some() || self.last_skip.is_some()
+    }
+    fn can_undo(&self) -> bool {
+        true
+    }
+    fn undo_move(&mut self, m: &Self::Move) {
+        let undo = self.undo_history.pop().expect("No move to undo");
+        if self.last_skip.is_none() {
+            self.switch_turn();
+        }
+        self.set_last_skip(undo.last_skip);
+        let piece = match self.board[m.1].clone() {
+            Some(p) if undo.crowned && p.is_o() => Some(Piece::O),
+            Some(_) if undo.crowned => Some(Piece::X),
+            p => p,
+        };
+        self.set_square(m.1, None);
+        self.set_square(m.0, piece);
+        if let Some(jumped) = m.2 {
+            self.set_square(jumped, undo.captured);
         }
-        !self.is_o_turn
     }
-    fn possible_moves(&self) -> Box<Iterator<Item = (usize, usize, Option<usize>)> + '_> {
+    fn to_move(&self) -> Player {
+        // The computer agent plays x, so x is Max
+        if self.is_o_turn {
+            Player::Min
+        } else {
+            Player::Max
+        }
+    }
+    fn outcome(&self) -> Outcome {
+        match self.is_o_winner() {
+            Some(true) => Outcome::Loss,
+            Some(false) => Outcome::Win,
+            // A player who cannot move loses
+            None if self.possible_moves().next().is_none() => match self.to_move() {
+                Player::Max => Outcome::Loss,
+                Player::Min => Outcome::Win,
+            },
+            None => Outcome::Ongoing,
+        }
+    }
+    fn position_key(&self) -> Option<u64> {
+        Some(self.hash)
+    }
+    fn move_priority(&self, m: &Self::Move) -> isize {
+        // Crowning a piece is usually worth it, then prefer moving into the middle of the board
+        let crowns = !self.board[m.0].as_ref().unwrap().is_king()
+            && (m.1 < BOARDWIDTH / 2 || m.1 >= BOARDSIZE - BOARDWIDTH / 2);
+        let column = (m.1 % BOARDWIDTH) % (BOARDWIDTH / 2);
+        let central = column > 0 && column < BOARDWIDTH / 2 - 1;
+        2 * crowns as isize + central as isize
+    }
+    fn possible_moves(&self) -> Box<dyn Iterator<Item = (usize, usize, Option<usize>)> + '_> {
         let mut positions: Vec<(usize, usize, Option<usize>)> = Vec::new();
         if let Some(p) = self.last_skip {
             positions.extend(&self.possible_positions_jump(p));
@@ -328,16 +479,45 @@ impl<'a> HeuristicGameTree for CheckersGame {
         Box::new(positions.into_iter())
     }
 }
+
+/// Function: Builds an opening book for Checkers by self-play, see `general_game::build_book`.
+/// The searches that choose the moves go 8 plies deep.
+/// # Arguments
+/// * `games` - How many games to play against itself
+pub fn book_checkers(games: usize) -> OpeningBook {
+    let mut builder = BookBuilder::new(BOOK_PLIES, 8).with_rng(Rng::from_clock());
+    builder.self_play(&CheckersGame::new(), games);
+    builder.build()
+}
+
 /// Starts the Checkers game
 ///
 /// # Arguments
 ///
 /// * `difficulty` - A usize that holds the difficulty of the game. This value is passed as
-///                  an argument to minimax search, determining the depth of the minimax search
-///                  tree (i.e. the number of steps ahead that the AI agent should look ahead when
-///                  determining its move)
-pub fn start_checkers(difficulty: usize) {
+///   an argument to minimax search, determining the depth of the minimax search
+///   tree (i.e. the number of steps ahead that the AI agent should look ahead when
+///   determining its move)
+///
+/// * `engine` - An Engine that holds how the computer agent chooses its moves
+///
+/// * `book` - The opening book the minimax engine plays its first moves from
+pub fn start_checkers(difficulty: usize, engine: Engine, book: &OpeningBook) {
     let mut game = CheckersGame::new();
+    // Shows how far the computer got while it thinks
+    let mut searcher = computer_searcher().on_progress(|progress: &minimax::SearchResult<_>| {
+        print!(
+            "\rOpponent's move ... depth {}, score {}, {} positions, {:.1}s ",
+            progress.depth,
+            progress.score,
+            progress.nodes,
+            progress.elapsed.as_secs_f64()
+        );
+        let _ = stdout().flush();
+    });
+    let mut mcts = Mcts::new(Rng::from_clock());
+    // Picks between the moves of the opening book
+    let mut rng = Rng::from_clock();
     //println!("Enter start location and end location as such:  B6 A5. You are o");
     println!("Enter start location and end location as such:  B6 A5. You are o");
     while game.who_won().is_none() {
@@ -347,10 +527,14 @@ pub fn start_checkers(difficulty: usize) {
         while game.is_opponent_turn() {
             print!("Opponent's move ... ");
             std::io::stdout().flush().unwrap();
-            let next_move = minimax::minimax_search(&game, difficulty * 6, true);
-            println!("Done");
+            let next_move = match engine {
+                Engine::Minimax => book_move(book, &game, difficulty, &mut rng)
+                    .or_else(|| searcher.search_nodes(&game, MAX_DEPTH, node_budget(difficulty))),
+                Engine::MonteCarlo => mcts.search_timed(&game, think_time(difficulty)),
+            };
+            println!("... Done");
             if let Some(m) = next_move {
-                game.execute_move(&m, true);
+                game.execute_move(&m);
             } else {
                 game.give_up_turn();
             }
@@ -413,7 +597,7 @@ pub fn start_checkers(difficulty: usize) {
             }
         }
         if let Some(mymove) = game.valid_move(start as usize, end as usize) {
-            game.execute_move(&mymove, false);
+            game.execute_move(&mymove);
             game.check_winner();
         } else {
             println!("Invalid move (remember if you have a jump, you must take it");
@@ -425,9 +609,15 @@ pub fn start_checkers(difficulty: usize) {
 
 #[cfg(test)]
 mod check_tests {
-    use super::CheckersGame;
-    use crate::HeuristicGameTree;
     use super::Piece;
+    use super::{print_piece, CheckersGame, BOARDSIZE};
+    use crate::general_game::{node_budget, MAX_DEPTH};
+    use crate::mcts::{mcts_search, Mcts};
+    use crate::minimax::{minimax_analyze, SearchConfig, Searcher, WIN_SCORE};
+    use crate::rng::Rng;
+    use crate::HeuristicGameTree;
+    use crate::{Outcome, Player};
+    use std::time::Duration;
 
     #[test]
     fn no_winner_test() {
@@ -455,12 +645,13 @@ mod check_tests {
 
     #[test]
     fn move_piece_test() {
-    	let mut game = CheckersGame::new();
-    	let mut output = Vec::new();
-    	game.execute_move(&(20,16,None),false);
-    	game.print_board(&mut output).unwrap();
-    	assert_eq!(&String::from_utf8(output).unwrap(),
-    		&"  A B C D E F G H
+        let mut game = CheckersGame::new();
+        let mut output = Vec::new();
+        game.execute_move(&(20, 16, None));
+        game.print_board(&mut output).unwrap();
+        assert_eq!(
+            &String::from_utf8(output).unwrap(),
+            &"  A B C D E F G H
 1 x   x   x   x
 2   x   x   x   x
 3 x   x   x   x
@@ -474,15 +665,16 @@ mod check_tests {
 
     #[test]
     fn jump_piece_test() {
-    	let mut game = CheckersGame::new();
-    	let mut output = Vec::new();
-    	game.execute_move(&(20,17,None),false);
-    	game.execute_move(&(9,12,None),false);
-    	game.execute_move(&(21,18,None),false);
-    	game.execute_move(&(12,21,Some(17)),false);
-    	game.print_board(&mut output).unwrap();
-    	assert_eq!(&String::from_utf8(output).unwrap(),
-    		&"  A B C D E F G H
+        let mut game = CheckersGame::new();
+        let mut output = Vec::new();
+        game.execute_move(&(20, 17, None));
+        game.execute_move(&(9, 12, None));
+        game.execute_move(&(21, 18, None));
+        game.execute_move(&(12, 21, Some(17)));
+        game.print_board(&mut output).unwrap();
+        assert_eq!(
+            &String::from_utf8(output).unwrap(),
+            &"  A B C D E F G H
 1 x   x   x   x
 2   x   x   x   x
 3 x       x   x
@@ -508,52 +700,355 @@ mod check_tests {
 
     #[test]
     fn force_jump_test() {
-    	let mut game = CheckersGame::new();
-    	game.execute_move(&(20,17,None),false);
-    	game.execute_move(&(9,12,None),false);
-    	game.execute_move(&(21,18,None),false);
-    	let moves: Vec<(usize,usize,Option<usize>)> = game.possible_moves().collect();
-    	assert_eq!(moves.len(),1);
-    	// Only allows one move, the jump move, see board in jump_piece_test()
+        let mut game = CheckersGame::new();
+        game.execute_move(&(20, 17, None));
+        game.execute_move(&(9, 12, None));
+        game.execute_move(&(21, 18, None));
+        let moves: Vec<(usize, usize, Option<usize>)> = game.possible_moves().collect();
+        assert_eq!(moves.len(), 1);
+        // Only allows one move, the jump move, see board in jump_piece_test()
     }
 
     #[test]
     fn force_repeated_jump_test() {
-    	let mut game = CheckersGame::new();
-    	game.board = [
-    		None,Some(Piece::X),None,Some(Piece::X),
-    		Some(Piece::O),Some(Piece::O),Some(Piece::O),None,
-    		None,None,None,None,
-    		Some(Piece::O),None,None,None,
-    		None,None,None,None,
-    		None,None,None,None,
-    		None,None,None,None,
-    		None,None,None,None,
-    	];
-    	game.is_o_turn = false;
-    	// easier than trying to move the board into this position
-    	let moves: Vec<(usize,usize,Option<usize>)> = game.possible_moves().collect();
-    	assert_eq!(moves.len(),3);
-    	// Do one jump
-    	game.execute_move(&(1,8,Some(4)),false);
-    	let moves: Vec<(usize,usize,Option<usize>)> = game.possible_moves().collect();
-    	// Only one jump available from the piece that just jumped.
-    	assert_eq!(moves.len(),1);
-    	assert!(game.is_opponent_turn());
+        let mut game = CheckersGame::new();
+        game.board = [
+            None,
+            Some(Piece::X),
+            None,
+            Some(Piece::X),
+            Some(Piece::O),
+            Some(Piece::O),
+            Some(Piece::O),
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(Piece::O),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ];
+        game.is_o_turn = false;
+        // easier than trying to move the board into this position
+        let moves: Vec<(usize, usize, Option<usize>)> = game.possible_moves().collect();
+        assert_eq!(moves.len(), 3);
+        // Do one jump
+        game.execute_move(&(1, 8, Some(4)));
+        let moves: Vec<(usize, usize, Option<usize>)> = game.possible_moves().collect();
+        // Only one jump available from the piece that just jumped.
+        assert_eq!(moves.len(), 1);
+        assert!(game.is_opponent_turn());
+    }
+
+    #[test]
+    fn multi_jump_search_test() {
+        let mut game = CheckersGame::new();
+        #[rustfmt::skip]
+        let board = [
+            None, Some(Piece::X), None, Some(Piece::X),
+            Some(Piece::O), Some(Piece::O), Some(Piece::O), None,
+            None, None, None, None,
+            Some(Piece::O), None, None, None,
+            None, None, None, None,
+            None, None, None, None,
+            None, None, None, None,
+            None, None, None, None,
+        ];
+        game.board = board;
+        game.is_o_turn = false;
+        game.hash = game.full_hash();
+        assert_eq!(game.to_move(), Player::Max);
+        let result = minimax_analyze(&game, 3);
+        // x takes both pieces in a row, keeping the turn in between
+        assert_eq!(result.principal_variation[0], (1, 8, Some(4)));
+        assert_eq!(result.principal_variation[1], (8, 17, Some(12)));
+    }
+
+    #[test]
+    fn outcome_test() {
+        let mut game = CheckersGame::new();
+        assert_eq!(game.outcome(), Outcome::Ongoing);
+        #[rustfmt::skip]
+        let board = [
+            None, None, None, None,
+            None, None, None, None,
+            None, Some(Piece::X), None, None,
+            None, Some(Piece::O), None, None,
+            None, None, None, None,
+            None, None, None, None,
+            None, None, None, None,
+            None, None, None, None,
+        ];
+        game.board = board;
+        game.is_o_turn = false;
+        game.hash = game.full_hash();
+        // x jumps the last o
+        let result = minimax_analyze(&game, 2);
+        assert_eq!(result.best_move, Some((9, 18, Some(13))));
+        assert_eq!(result.score, WIN_SCORE - 1);
+        game.execute_move(&(9, 18, Some(13)));
+        assert_eq!(game.outcome(), Outcome::Win);
+    }
+
+    #[test]
+    fn undo_test() {
+        let mut game = CheckersGame::new();
+        #[rustfmt::skip]
+        let board = [
+            None, None, None, None,
+            None, Some(Piece::X), None, None,
+            None, Some(Piece::O), None, None,
+            None, None, None, None,
+            None, Some(Piece::O), None, None,
+            None, None, None, None,
+            None, None, None, None,
+            None, None, None, None,
+        ];
+        game.board = board;
+        game.is_o_turn = false;
+        game.hash = game.full_hash();
+        let start = game.clone();
+        // x jumps twice in a row
+        let moves = [(5, 12, Some(9)), (12, 21, Some(17))];
+        for m in moves.iter() {
+            game.execute_move(m);
+        }
+        assert!(game.is_o_winner() == Some(false));
+        assert_eq!(game.undo_history.len(), 2);
+        assert!(game.clone().undo_history.is_empty());
+        for m in moves.iter().rev() {
+            game.undo_move(m);
+        }
+        assert_eq!(game.hash, start.hash);
+        assert_eq!(game.last_skip, None);
+        assert!(!game.is_o_turn);
+        for i in 0..BOARDSIZE {
+            assert_eq!(print_piece(&game.board[i]), print_piece(&start.board[i]));
+        }
+        // o is crowned, and uncrowned again
+        game.board[4] = Some(Piece::O);
+        game.is_o_turn = true;
+        game.hash = game.full_hash();
+        let before = game.clone();
+        game.execute_move(&(4, 0, None));
+        assert_eq!(print_piece(&game.board[0]), "O");
+        game.undo_move(&(4, 0, None));
+        assert_eq!(game.hash, before.hash);
+        assert_eq!(print_piece(&game.board[4]), "o");
+        assert_eq!(print_piece(&game.board[0]), " ");
+        assert!(game.is_o_turn);
+    }
+
+    #[test]
+    fn parallel_test() {
+        let mut game = CheckersGame::new();
+        game.execute_move(&(20, 16, None));
+        let single = minimax_analyze(&game, 6);
+        let mut parallel = Searcher::new().with_config(SearchConfig {
+            threads: 4,
+            ..SearchConfig::default()
+        });
+        let result = parallel.analyze_parallel(&game, 6);
+        assert_eq!(result.score, single.score);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn selective_test() {
+        let mut game = CheckersGame::new();
+        game.execute_move(&(20, 16, None));
+        let full = Searcher::new().analyze(&game, 7);
+        let mut selective = Searcher::new().with_config(SearchConfig {
+            late_move_reductions: true,
+            futility_margin: Some(1),
+            ..SearchConfig::default()
+        });
+        let result = selective.analyze(&game, 7);
+        assert!(game.possible_moves().any(|m| Some(m) == result.best_move));
+        assert!(result.nodes < full.nodes);
+    }
+
+    #[test]
+    fn node_budget_test() {
+        let mut game = CheckersGame::new();
+        game.execute_move(&(20, 16, None));
+        let first = Searcher::new().analyze_nodes(&game, MAX_DEPTH, node_budget(1));
+        let again = Searcher::new().analyze_nodes(&game, MAX_DEPTH, node_budget(1));
+        assert_eq!(first.best_move, again.best_move);
+        assert_eq!((first.depth, first.nodes), (again.depth, again.nodes));
+        assert!(first.nodes <= node_budget(1) + 1);
+        assert!(
+            Searcher::new()
+                .analyze_nodes(&game, MAX_DEPTH, node_budget(2))
+                .depth
+                > first.depth
+        );
+    }
+
+    #[test]
+    fn pvs_test() {
+        let mut game = CheckersGame::new();
+        game.execute_move(&(20, 16, None));
+        game.execute_move(&(9, 13, None));
+        let plain = Searcher::new()
+            .with_config(SearchConfig {
+                pvs: false,
+                ..SearchConfig::default()
+            })
+            .analyze(&game, 6);
+        assert_eq!(minimax_analyze(&game, 6).score, plain.score);
+        let mut aspiration = Searcher::new().with_config(SearchConfig {
+            aspiration_window: Some(1),
+            ..SearchConfig::default()
+        });
+        let result = aspiration.analyze_timed(&game, 6, Duration::from_secs(60));
+        assert_eq!(result.depth, 6);
+        assert_eq!(result.score, plain.score);
+    }
+
+    #[test]
+    fn mcts_test() {
+        let mut game = CheckersGame::new();
+        #[rustfmt::skip]
+        let board = [
+            None, None, None, None,
+            None, None, None, None,
+            None, Some(Piece::X), None, None,
+            None, Some(Piece::O), None, None,
+            None, None, None, None,
+            None, None, None, None,
+            None, None, None, None,
+            None, None, None, None,
+        ];
+        game.board = board;
+        game.is_o_turn = false;
+        game.hash = game.full_hash();
+        assert_eq!(mcts_search(&game, 100, 11), Some((9, 18, Some(13))));
+        let mut game = CheckersGame::new();
+        game.execute_move(&(20, 16, None));
+        let result = Mcts::new(Rng::new(3)).analyze(&game, 200);
+        assert!(result.best_move.is_some());
+        assert_eq!(result.iterations, 200);
+    }
+
+    #[test]
+    fn quiescence_test() {
+        let mut game = CheckersGame::new();
+        #[rustfmt::skip]
+        let board = [
+            None, None, None, None,
+            None, None, None, None,
+            None, Some(Piece::X), None, None,
+            None, Some(Piece::O), None, None,
+            None, None, None, None,
+            None, None, Some(Piece::O), None,
+            None, None, None, Some(Piece::X),
+            None, None, None, None,
+        ];
+        game.board = board;
+        game.is_o_turn = false;
+        game.hash = game.full_hash();
+        // x has to jump, and o jumps straight back
+        let mut quiet = Searcher::new().with_config(SearchConfig {
+            quiescence: false,
+            ..SearchConfig::default()
+        });
+        assert_eq!(quiet.analyze(&game, 1).score, 1);
+        let result = minimax_analyze(&game, 1);
+        assert_eq!(result.score, 0);
+        assert_eq!(
+            result.principal_variation,
+            vec![(9, 18, Some(13)), (22, 13, Some(18))]
+        );
+    }
+
+    #[test]
+    fn undo_search_test() {
+        let mut game = CheckersGame::new();
+        game.execute_move(&(20, 16, None));
+        let mut cloning = Searcher::new().with_config(SearchConfig {
+            undo_moves: false,
+            ..SearchConfig::default()
+        });
+        let undone = Searcher::new().analyze(&game, 6);
+        let cloned = cloning.analyze(&game, 6);
+        assert_eq!(undone.best_move, cloned.best_move);
+        assert_eq!(undone.score, cloned.score);
+        assert_eq!(undone.principal_variation, cloned.principal_variation);
+        assert_eq!(undone.nodes, cloned.nodes);
+    }
+
+    #[test]
+    fn hash_test() {
+        let mut game = CheckersGame::new();
+        let start = game.hash;
+        game.execute_move(&(20, 17, None));
+        game.execute_move(&(9, 12, None));
+        game.execute_move(&(21, 18, None));
+        game.execute_move(&(12, 21, Some(17)));
+        assert_eq!(game.hash, game.full_hash());
+        assert_ne!(game.hash, start);
+        game.give_up_turn();
+        assert_eq!(game.hash, game.full_hash());
+    }
+
+    #[test]
+    fn hash_transposition_test() {
+        let mut a = CheckersGame::new();
+        a.execute_move(&(20, 16, None));
+        a.execute_move(&(9, 13, None));
+        a.execute_move(&(21, 17, None));
+        let mut b = CheckersGame::new();
+        b.execute_move(&(21, 17, None));
+        b.execute_move(&(9, 13, None));
+        b.execute_move(&(20, 16, None));
+        assert_eq!(a.hash, b.hash);
+    }
+
+    #[test]
+    fn ordering_test() {
+        let mut game = CheckersGame::new();
+        game.execute_move(&(20, 16, None));
+        let mut ordered = Searcher::new();
+        let mut unordered = Searcher::new().with_config(SearchConfig {
+            move_ordering: false,
+            ..SearchConfig::default()
+        });
+        ordered.search(&game, 8);
+        unordered.search(&game, 8);
+        assert!(ordered.nodes() < unordered.nodes());
     }
 
     #[test]
     fn heuristic_test() {
-    	let mut game = CheckersGame::new();
-    	assert_eq!(game.heuristic(),0);
-    	game.execute_move(&(20,17,None),false);
-    	assert_eq!(game.heuristic(),0);
-    	game.execute_move(&(9,12,None),false);
-    	assert_eq!(game.heuristic(),0);
-    	game.execute_move(&(21,18,None),false);
-    	assert_eq!(game.heuristic(),0);
-    	game.execute_move(&(12,21,Some(17)),false);
-    	assert_eq!(game.heuristic(),1);
+        let mut game = CheckersGame::new();
+        assert_eq!(game.heuristic(), 0);
+        game.execute_move(&(20, 17, None));
+        assert_eq!(game.heuristic(), 0);
+        game.execute_move(&(9, 12, None));
+        assert_eq!(game.heuristic(), 0);
+        game.execute_move(&(21, 18, None));
+        assert_eq!(game.heuristic(), 0);
+        game.execute_move(&(12, 21, Some(17)));
+        assert_eq!(game.heuristic(), 1);
     }
 
     #[test]
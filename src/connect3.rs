@@ -0,0 +1,188 @@
+use crate::multiplayer::MultiPlayerGame;
+use std::sync::OnceLock;
+
+// The size of the board, and how many pieces in a row win
+const COLUMNS: usize = 5;
+const ROWS: usize = 4;
+const IN_A_ROW: usize = 3;
+const PLAYERS: usize = 3;
+
+/// Connect three for three players, a small game for the multi-player searches.
+///
+/// Players 0, 1 and 2 take turns dropping a piece into one of the 5 columns of a board that
+/// is 4 rows high, where it falls to the lowest free row. The first player with three pieces
+/// in a row, across, up or diagonally, wins, and a full board is a draw.
+///
+/// The heuristic of a player counts the lines of three that only that player has pieces in,
+/// with a line worth more the more pieces it holds.
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::connect3::Connect3;
+/// # use heuristic_game_tree::multiplayer::{maxn_search, MultiPlayerGame};
+/// let mut game = Connect3::new();
+/// for column in [0, 4, 4, 2, 3, 3] {
+///     game.execute_move(&column);
+/// }
+/// // Player 0 completes the bottom row
+/// assert_eq!(maxn_search(&game, 3), Some(1));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Connect3 {
+    board: [[Option<usize>; ROWS]; COLUMNS],
+    to_move: usize,
+    winner: Option<usize>,
+}
+
+impl Default for Connect3 {
+    fn default() -> Self {
+        Connect3::new()
+    }
+}
+
+impl Connect3 {
+    /// Creates an empty board, with player 0 to move.
+    pub fn new() -> Self {
+        Connect3 {
+            board: [[None; ROWS]; COLUMNS],
+            to_move: 0,
+            winner: None,
+        }
+    }
+
+    /// Returns the player whose piece is at a column and row, counting rows from the bottom.
+    pub fn piece(&self, column: usize, row: usize) -> Option<usize> {
+        self.board[column][row]
+    }
+
+    // Every line of three squares on the board, worked out on first use
+    fn lines() -> &'static [[(usize, usize); IN_A_ROW]] {
+        static LINES: OnceLock<Vec<[(usize, usize); IN_A_ROW]>> = OnceLock::new();
+        LINES.get_or_init(|| {
+            let mut lines = Vec::new();
+            for column in 0..COLUMNS as isize {
+                for row in 0..ROWS as isize {
+                    for (dc, dr) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                        let square = |i: isize| {
+                            let (c, r) = (column + dc * i, row + dr * i);
+                            (c >= 0 && c < COLUMNS as isize && r >= 0 && r < ROWS as isize)
+                                .then_some((c as usize, r as usize))
+                        };
+                        if let (Some(a), Some(b), Some(c)) = (square(0), square(1), square(2)) {
+                            lines.push([a, b, c]);
+                        }
+                    }
+                }
+            }
+            lines
+        })
+    }
+
+    // Whether the piece just dropped at a square completes a line; no other line can have changed
+    fn wins_through(&self, column: usize, row: usize) -> bool {
+        let player = self.board[column][row];
+        Self::lines()
+            .iter()
+            .filter(|line| line.contains(&(column, row)))
+            .any(|line| line.iter().all(|&(c, r)| self.board[c][r] == player))
+    }
+}
+
+impl MultiPlayerGame for Connect3 {
+    type Move = usize;
+    type Score = isize;
+    fn players(&self) -> usize {
+        PLAYERS
+    }
+    fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+        let open = self.winner.is_none();
+        Box::new((0..COLUMNS).filter(move |column| open && self.board[*column][ROWS - 1].is_none()))
+    }
+    fn heuristic(&self) -> Vec<isize> {
+        let mut scores = vec![0; PLAYERS];
+        for line in Self::lines() {
+            let pieces: Vec<usize> = line.iter().filter_map(|&(c, r)| self.board[c][r]).collect();
+            if let Some(&player) = pieces.first() {
+                if pieces.iter().all(|piece| *piece == player) {
+                    scores[player] += (pieces.len() * pieces.len()) as isize;
+                }
+            }
+        }
+        scores
+    }
+    fn execute_move(&mut self, next_move: &Self::Move) {
+        if let Some(row) = (0..ROWS).find(|row| self.board[*next_move][*row].is_none()) {
+            self.board[*next_move][row] = Some(self.to_move);
+            if self.wins_through(*next_move, row) {
+                self.winner = Some(self.to_move);
+            }
+            self.to_move = (self.to_move + 1) % PLAYERS;
+        }
+    }
+    fn to_move(&self) -> usize {
+        self.to_move
+    }
+    fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+}
+
+#[cfg(test)]
+mod connect3_tests {
+    use super::Connect3;
+    use crate::multiplayer::{
+        maxn_analyze, maxn_search, paranoid_analyze, paranoid_search, MultiPlayerGame,
+    };
+
+    fn play(columns: &[usize]) -> Connect3 {
+        let mut game = Connect3::new();
+        for column in columns {
+            game.execute_move(column);
+        }
+        game
+    }
+
+    #[test]
+    fn lines_test() {
+        // 3 across in every row, 2 up in every column, and 3 each way diagonally for 2 rows
+        assert_eq!(Connect3::lines().len(), 3 * 4 + 2 * 5 + 2 * 3 * 2);
+    }
+
+    #[test]
+    fn winner_test() {
+        let game = play(&[0, 0, 1, 1, 2]);
+        assert_eq!(game.winner(), None);
+        assert_eq!(game.piece(0, 1), Some(1));
+        assert_eq!(game.to_move(), 2);
+        let game = play(&[0, 4, 4, 1, 3, 3, 2]);
+        assert_eq!(game.winner(), Some(0));
+        assert_eq!(game.possible_moves().count(), 0);
+    }
+
+    #[test]
+    fn heuristic_test() {
+        assert_eq!(Connect3::new().heuristic(), vec![0, 0, 0]);
+        // A corner piece takes part in 3 lines, of one piece each
+        assert_eq!(play(&[0]).heuristic(), vec![3, 0, 0]);
+        // The middle of the bottom row takes part in 6 lines, and shares one with the corner
+        assert_eq!(play(&[0, 2]).heuristic(), vec![2, 5, 0]);
+    }
+
+    #[test]
+    fn takes_win_test() {
+        let game = play(&[0, 4, 4, 2, 3, 3]);
+        assert_eq!(maxn_search(&game, 3), Some(1));
+        assert_eq!(paranoid_search(&game, 3), Some(1));
+    }
+
+    #[test]
+    fn maxn_paranoid_test() {
+        let game = play(&[2, 1, 3]);
+        let maxn = maxn_analyze(&game, 4);
+        let paranoid = paranoid_analyze(&game, 4);
+        assert!(maxn.best_move.is_some() && paranoid.best_move.is_some());
+        assert_eq!(maxn.principal_variation.len(), 4);
+        // Pruning lets paranoid search look at fewer positions
+        assert!(paranoid.nodes < maxn.nodes);
+    }
+}
@@ -1,13 +1,24 @@
 use super::*;
+use crate::book::{BookBuilder, OpeningBook};
+use crate::general_game::computer_searcher;
 use crate::general_game::print_piece;
+use crate::general_game::think_time;
+use crate::general_game::Engine;
 use crate::general_game::Piece;
-use crate::minimax;
+use crate::general_game::{book_move, BOOK_PLIES};
+use crate::general_game::{node_budget, MAX_DEPTH};
+use crate::mcts::Mcts;
+use crate::rng::Rng;
+use crate::zobrist::ZobristTable;
 use std::cmp::max;
 use std::io::{stdin, stdout, Write};
 
-impl<'a> HeuristicGameTree for ConGame {
+static ZOBRIST: ZobristTable<42, 2> = ZobristTable::new(0x636f_6e6e_6563_7434);
+
+impl HeuristicGameTree for ConGame {
     type Move = usize;
-    fn possible_moves(&self) -> Box<Iterator<Item = Self::Move> + '_> {
+    type Score = isize;
+    fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
         let mut list = Vec::new();
         for i in 0..7 {
             if self.board[i][5].is_none() {
@@ -16,15 +27,14 @@ impl<'a> HeuristicGameTree for ConGame {
         }
         Box::new(list.into_iter())
     }
-    fn heuristic(&self) -> isize {
+    fn heuristic(&self) -> Self::Score {
         let mut x_streak = 0;
         let mut o_streak = 0;
         // First check for wins
-        let mut mutableself = self.clone();
         'outer: for a in 0..6 {
             for b in 0..5 {
-                if mutableself.board[a][b] == Some(Piece::X) {
-                    let cur = mutableself.check_win_and_length(a, b, Piece::X).1;
+                if self.board[a][b] == Some(Piece::X) {
+                    let cur = self.check_win_and_length(a, b, Piece::X).1;
                     x_streak = max(cur, x_streak);
                     if x_streak >= 4 {
                         x_streak = 4;
@@ -35,8 +45,8 @@ impl<'a> HeuristicGameTree for ConGame {
         }
         'outer2: for a in 0..6 {
             for b in 0..5 {
-                if mutableself.board[a][b] == Some(Piece::O) {
-                    let cur = mutableself.check_win_and_length(a, b, Piece::O).1;
+                if self.board[a][b] == Some(Piece::O) {
+                    let cur = self.check_win_and_length(a, b, Piece::O).1;
                     o_streak = max(cur, o_streak);
                     if o_streak >= 4 {
                         o_streak = 4;
@@ -46,22 +56,67 @@ impl<'a> HeuristicGameTree for ConGame {
             }
         }
 
+        // O is the computer agent, so O is Max
         if x_streak == 4 {
-            x_streak
+            -x_streak
         } else if o_streak == 4 {
-            -o_streak
+            o_streak
         } else {
-            x_streak - o_streak
+            o_streak - x_streak
         }
     }
-    fn execute_move(&mut self, next_move: &Self::Move, is_opponent: bool) -> bool {
+    fn execute_move(&mut self, next_move: &Self::Move) {
         let (_val, loc) = self.clone().validmove(next_move + 1);
-        self.store_move(
-            *next_move,
-            loc,
-            if is_opponent { Piece::X } else { Piece::O },
-        );
-        !is_opponent
+        let piece = match self.to_move() {
+            Player::Max => Piece::O,
+            Player::Min => Piece::X,
+        };
+        self.store_move(*next_move, loc, piece);
+    }
+    fn to_move(&self) -> Player {
+        // X goes first, so it is X's turn whenever both have played as often
+        let mut x_count = 0;
+        let mut o_count = 0;
+        for piece in self.board.iter().flatten().flatten() {
+            match piece {
+                Piece::X => x_count += 1,
+                Piece::O => o_count += 1,
+            }
+        }
+        if x_count == o_count {
+            Player::Min
+        } else {
+            Player::Max
+        }
+    }
+    fn outcome(&self) -> Outcome {
+        // O is the computer agent, so O is Max
+        match self.winner {
+            Some(Piece::O) => Outcome::Win,
+            Some(Piece::X) => Outcome::Loss,
+            None if !self.board_not_full() => Outcome::Draw,
+            None => Outcome::Ongoing,
+        }
+    }
+    fn position_key(&self) -> Option<u64> {
+        Some(self.hash)
+    }
+    fn can_undo(&self) -> bool {
+        true
+    }
+    fn undo_move(&mut self, last_move: &Self::Move) {
+        let col = *last_move;
+        if let Some(row) = (0..6).rev().find(|row| self.board[col][*row].is_some()) {
+            let old = self.board[col][row].take().unwrap();
+            self.hash ^= ZOBRIST.piece(col * 6 + row, old.index());
+            if self.winner.is_some() {
+                self.winner = self.find_winner();
+            }
+        }
+    }
+    fn move_priority(&self, next_move: &Self::Move) -> isize {
+        // Central columns take part in more lines of four
+        3 - (*next_move as isize - 3).abs()
     }
 }
 
@@ -71,6 +126,7 @@ impl<'a> HeuristicGameTree for ConGame {
 struct ConGame {
     board: [[Option<Piece>; 6]; 7],
     winner: Option<Piece>,
+    hash: u64,
 }
 
 impl ConGame {
@@ -78,6 +134,7 @@ impl ConGame {
         ConGame {
             board: [[None; 6]; 7],
             winner: None,
+            hash: 0,
         }
     }
 
@@ -99,7 +156,7 @@ impl ConGame {
     }
 
     fn validmove(self, col: usize) -> (bool, usize) {
-        if col >= 1 && col <= 7 {
+        if (1..=7).contains(&col) {
             let firstvec = col - 1;
 
             for i in 0..6 {
@@ -112,14 +169,35 @@ impl ConGame {
     }
 
     fn store_move(&mut self, col: usize, row: usize, player: Piece) {
+        if let Some(old) = self.board[col][row] {
+            self.hash ^= ZOBRIST.piece(col * 6 + row, old.index());
+        }
         self.board[col][row] = Some(player);
+        self.hash ^= ZOBRIST.piece(col * 6 + row, player.index());
+        if self.winner.is_none() && self.check_win_and_length(col, row, player).0 {
+            self.winner = Some(player);
+        }
     }
-    fn check_win_and_length(&mut self, col: usize, row: usize, player: Piece) -> (bool, isize) {
-        let mut lengths = Vec::new();
-        lengths.push(self.horizontal(col, row, player));
-        lengths.push(self.vertical(col, row, player));
-        lengths.push(self.lef_diag(col, row, player));
-        lengths.push(self.right_diag(col, row, player));
+    // Looks over the whole board for a line of four, which store_move only does around the new piece
+    fn find_winner(&self) -> Option<Piece> {
+        for col in 0..7 {
+            for row in 0..6 {
+                if let Some(player) = self.board[col][row] {
+                    if self.check_win_and_length(col, row, player).0 {
+                        return Some(player);
+                    }
+                }
+            }
+        }
+        None
+    }
+    fn check_win_and_length(&self, col: usize, row: usize, player: Piece) -> (bool, isize) {
+        let lengths = [
+            self.horizontal(col, row, player),
+            self.vertical(col, row, player),
+            self.lef_diag(col, row, player),
+            self.right_diag(col, row, player),
+        ];
         let longest = *lengths.iter().max().unwrap();
         let win = longest >= 4;
         (win, longest)
@@ -258,18 +336,36 @@ impl ConGame {
     }
 }
 
+/// Function: Builds an opening book for Connect4 by self-play, see `general_game::build_book`.
+/// The searches that choose the moves go 8 plies deep.
+/// # Arguments
+/// * `games` - How many games to play against itself
+pub fn book_con(games: usize) -> OpeningBook {
+    let mut builder = BookBuilder::new(BOOK_PLIES, 8).with_rng(Rng::from_clock());
+    builder.self_play(&ConGame::new(), games);
+    builder.build()
+}
+
 /// Starts the Connect4 game
 ///
 /// # Arguments
 ///
 /// * `difficulty` - A usize that holds the difficulty of the game. This value is passed as
-///                  an argument to minimax search, determining the depth of the minimax search
-///                  tree (i.e. the number of steps ahead that the AI agent should look ahead when
-///                  determining its move)
-pub fn start_con(difficulty: usize) {
+///   an argument to minimax search, determining the depth of the minimax search
+///   tree (i.e. the number of steps ahead that the AI agent should look ahead when
+///   determining its move)
+///
+/// * `engine` - An Engine that holds how the computer agent chooses its moves
+///
+/// * `book` - The opening book the minimax engine plays its first moves from
+pub fn start_con(difficulty: usize, engine: Engine, book: &OpeningBook) {
     let mut new_game = ConGame::new();
+    let mut searcher = computer_searcher();
+    let mut mcts = Mcts::new(Rng::from_clock());
+    // Picks between the moves of the opening book
+    let mut rng = Rng::from_clock();
 
-    while new_game.winner == None && new_game.board_not_full() {
+    while new_game.winner.is_none() && new_game.board_not_full() {
         println!("Where do you want to put your X? (Only input col)");
         new_game.printboard();
         let mut loc = String::new();
@@ -287,7 +383,13 @@ pub fn start_con(difficulty: usize) {
                 if new_game.check_win_and_length(col - 1, row, Piece::X).0 {
                     new_game.winner = Some(Piece::X);
                 } else {
-                    let next_move = minimax::minimax_search(&new_game, 3 * difficulty, true);
+                    let next_move = match engine {
+                        Engine::Minimax => book_move(book, &new_game, difficulty, &mut rng)
+                            .or_else(|| {
+                                searcher.search_nodes(&new_game, MAX_DEPTH, node_budget(difficulty))
+                            }),
+                        Engine::MonteCarlo => mcts.search_timed(&new_game, think_time(difficulty)),
+                    };
                     if let Some(m) = next_move {
                         let (_val, loc) = new_game.clone().validmove(m + 1);
                         new_game.store_move(m, loc, Piece::O);
@@ -318,6 +420,11 @@ mod con_tests {
     use super::print_piece;
     use super::ConGame;
     use super::Piece;
+    use super::ZOBRIST;
+    use crate::mcts::mcts_search;
+    use crate::minimax::{minimax_analyze, minimax_search, SearchConfig, Searcher};
+    use crate::pns::{pns_solve, Proof};
+    use crate::{HeuristicGameTree, Outcome, Player};
 
     #[test]
     fn new_con_test() {
@@ -339,6 +446,141 @@ mod con_tests {
         assert_eq!(print_piece(con_1.board[5][1]), "X");
     }
 
+    #[test]
+    fn ordering_con_test() {
+        let mut con_1 = ConGame::new();
+        con_1.store_move(3, 0, Piece::X);
+        con_1.store_move(3, 1, Piece::O);
+        con_1.store_move(2, 0, Piece::X);
+        let mut ordered = Searcher::new();
+        let mut unordered = Searcher::new().with_config(SearchConfig {
+            move_ordering: false,
+            ..SearchConfig::default()
+        });
+        ordered.search(&con_1, 8);
+        unordered.search(&con_1, 8);
+        assert!(ordered.nodes() < unordered.nodes());
+    }
+
+    #[test]
+    fn turn_con_test() {
+        let mut con_1 = ConGame::new();
+        assert_eq!(con_1.to_move(), Player::Min);
+        con_1.execute_move(&3);
+        assert_eq!(con_1.board[3][0], Some(Piece::X));
+        assert_eq!(con_1.to_move(), Player::Max);
+        con_1.execute_move(&3);
+        assert_eq!(con_1.board[3][1], Some(Piece::O));
+    }
+
+    #[test]
+    fn block_con_test() {
+        let mut con_1 = ConGame::new();
+        con_1.store_move(0, 0, Piece::X);
+        con_1.store_move(1, 0, Piece::O);
+        con_1.store_move(0, 1, Piece::X);
+        con_1.store_move(2, 0, Piece::O);
+        con_1.store_move(0, 2, Piece::X);
+        // O, the computer agent, has to block the column
+        assert_eq!(minimax_search(&con_1, 2), Some(0));
+    }
+
+    #[test]
+    fn parallel_con_test() {
+        let mut con_1 = ConGame::new();
+        con_1.execute_move(&3);
+        con_1.execute_move(&2);
+        con_1.execute_move(&3);
+        let single = minimax_analyze(&con_1, 6);
+        for threads in [2, 3] {
+            let mut parallel = Searcher::new().with_config(SearchConfig {
+                threads,
+                ..SearchConfig::default()
+            });
+            assert_eq!(parallel.analyze_parallel(&con_1, 6).score, single.score);
+        }
+    }
+
+    #[test]
+    fn mtdf_con_test() {
+        let mut con_1 = ConGame::new();
+        con_1.execute_move(&3);
+        con_1.execute_move(&2);
+        let plain = minimax_analyze(&con_1, 5);
+        let mut searcher = Searcher::new();
+        assert_eq!(searcher.analyze_mtdf(&con_1, 5).score, plain.score);
+        let score = searcher.alpha_beta(&con_1, 5, plain.score - 1, plain.score + 1);
+        assert_eq!(score, plain.score);
+    }
+
+    #[test]
+    fn mcts_con_test() {
+        let mut con_1 = ConGame::new();
+        con_1.store_move(0, 0, Piece::X);
+        con_1.store_move(0, 1, Piece::X);
+        con_1.store_move(0, 2, Piece::X);
+        con_1.store_move(1, 0, Piece::O);
+        con_1.store_move(2, 0, Piece::O);
+        assert_eq!(mcts_search(&con_1, 3000, 11), Some(0));
+    }
+
+    #[test]
+    fn pns_con_test() {
+        // X has three in a row along the bottom, open at both ends, so O can only block one end
+        let mut con_1 = ConGame::new();
+        for col in 2..5 {
+            con_1.store_move(col, 0, Piece::X);
+        }
+        con_1.store_move(2, 1, Piece::O);
+        con_1.store_move(3, 1, Piece::O);
+        let result = pns_solve(&con_1, 100_000);
+        assert_eq!(result.proof, Proof::Loss);
+        assert!(result.proof_size > 7);
+        con_1.execute_move(&1);
+        let result = pns_solve(&con_1, 100_000);
+        assert_eq!((result.proof, result.winning_move), (Proof::Win, Some(5)));
+        assert_eq!(result.proof_size, 2);
+    }
+
+    #[test]
+    fn outcome_con_test() {
+        let mut con_1 = ConGame::new();
+        for row in 0..3 {
+            con_1.store_move(6, row, Piece::X);
+            assert_eq!(con_1.outcome(), Outcome::Ongoing);
+        }
+        con_1.store_move(6, 3, Piece::X);
+        assert_eq!(con_1.outcome(), Outcome::Loss);
+    }
+
+    #[test]
+    fn undo_con_test() {
+        let mut con_1 = ConGame::new();
+        for col in [3, 3, 4, 4, 5, 5] {
+            con_1.execute_move(&col);
+        }
+        let before = con_1.clone();
+        con_1.execute_move(&6);
+        assert_eq!(con_1.outcome(), Outcome::Loss);
+        con_1.undo_move(&6);
+        assert_eq!(con_1.board, before.board);
+        assert_eq!(con_1.hash, before.hash);
+        assert_eq!(con_1.outcome(), Outcome::Ongoing);
+        con_1.undo_move(&5);
+        assert_eq!(con_1.board[5][1], None);
+        assert_eq!(con_1.board[5][0], Some(Piece::X));
+    }
+
+    #[test]
+    fn hash_con_test() {
+        let mut con_1 = ConGame::new();
+        con_1.store_move(3, 0, Piece::X);
+        con_1.store_move(3, 1, Piece::O);
+        con_1.store_move(3, 1, Piece::X);
+        let full = ZOBRIST.hash_pieces(vec![(18, 0), (19, 0)]);
+        assert_eq!(con_1.hash, full);
+    }
+
     #[test]
     fn valid_con_test() {
         let con_1 = ConGame::new();
@@ -368,19 +610,19 @@ mod con_tests {
                 con_1.store_move(a, b, Piece::X);
             }
         }
-        assert_eq!(con_1.board_not_full(), false);
+        assert!(!con_1.board_not_full());
     }
 
     #[test]
     fn board_not_full2_test() {
         let mut con_1 = ConGame::new();
         con_1.store_move(5, 1, Piece::X);
-        assert_eq!(con_1.board_not_full(), true);
+        assert!(con_1.board_not_full());
     }
 
     #[test]
     fn lose_con_test() {
-        let mut con_1 = ConGame::new();
+        let con_1 = ConGame::new();
         let a = con_1.check_win_and_length(5, 1, Piece::X).0;
         assert!(!a);
     }
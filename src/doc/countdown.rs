@@ -0,0 +1,33 @@
+// The game of the doc examples, which include this file: from a count, take 1 or 2 steps
+// on your turn, and whoever takes the last step wins. So a multiple of three is lost for the
+// player to move, and the winning move leaves the other player one.
+#[derive(Clone)]
+struct Countdown(usize, Player);
+
+impl HeuristicGameTree for Countdown {
+    type Move = usize;
+    type Score = isize;
+    fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+        Box::new((1..=2).filter(move |step| *step <= self.0))
+    }
+    fn heuristic(&self) -> isize {
+        0
+    }
+    fn execute_move(&mut self, step: &Self::Move) {
+        self.0 -= step;
+        self.1 = self.1.opponent();
+    }
+    fn to_move(&self) -> Player {
+        self.1
+    }
+    fn outcome(&self) -> Outcome {
+        match (self.0, self.1) {
+            (0, Player::Max) => Outcome::Loss,
+            (0, Player::Min) => Outcome::Win,
+            _ => Outcome::Ongoing,
+        }
+    }
+    fn position_key(&self) -> Option<u64> {
+        Some(self.0 as u64)
+    }
+}
@@ -0,0 +1,830 @@
+use super::{HeuristicGameTree, Outcome, Player, Score, WholeScore};
+use crate::minimax::{evaluate, SearchResult};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::time::Instant;
+
+/// Function: Expectimax with alpha-beta pruning, for games of chance.
+/// In a game with dice or cards, some positions are not a player's turn but a random event
+/// (see `HeuristicGameTree::chance_outcomes`). Minimax would assume the worst event always
+/// happens. Expectimax instead scores such a chance node by the average of its events, weighted
+/// by how likely they are, and so plays for the best expected result. Player's turns are
+/// searched as in `minimax_search`, with Max maximizing and Min minimizing the heuristic.
+///
+/// Chance nodes are pruned with Star1 and Star2: once the events searched so far show that the
+/// average cannot reach the window of alpha-beta, the remaining events are skipped. This needs
+/// to know the lowest and highest score any position can have, which is given by
+/// `ExpectimaxConfig::score_bounds`. The default bounds always hold, but the tighter the bounds,
+/// the more can be pruned.
+///
+/// Averages are kept as exact fractions of the heuristic, so close choices are told apart even
+/// when their expected values differ by less than a point. The fractions are built from whole
+/// numbers, so expectimax searches games whose scores are whole numbers (see `WholeScore`).
+///
+/// # Arguments
+/// * `depth` - A usize that holds how many player moves to look ahead; random events do not count
+///
+/// Returns the move with the best expected heuristic for the player to move, or None if the
+/// position is a chance node or has no possible moves
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::{HeuristicGameTree, Player};
+/// # use heuristic_game_tree::expectimax::expectimax_search;
+/// // Max either takes 1 point, or flips a coin that pays 5 points on heads and -1 on tails
+/// #[derive(Clone)]
+/// struct Flip(Option<&'static str>);
+/// impl HeuristicGameTree for Flip {
+///     type Move = &'static str;
+///     type Score = isize;
+///     fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+///         match self.0 {
+///             None => Box::new(vec!["take", "flip"].into_iter()),
+///             Some(_) => Box::new(std::iter::empty()),
+///         }
+///     }
+///     fn heuristic(&self) -> isize {
+///         match self.0 {
+///             Some("take") => 1,
+///             Some("heads") => 5,
+///             Some("tails") => -1,
+///             _ => 0,
+///         }
+///     }
+///     fn execute_move(&mut self, next_move: &Self::Move) { self.0 = Some(*next_move); }
+///     fn to_move(&self) -> Player { Player::Max }
+///     fn chance_outcomes(&self) -> Vec<(Self::Move, u32)> {
+///         match self.0 {
+///             Some("flip") => vec![("heads", 1), ("tails", 1)],
+///             _ => Vec::new(),
+///         }
+///     }
+/// }
+///
+/// // The coin is worth 2 points on average, more than the safe point
+/// assert_eq!(expectimax_search(&Flip(None), 1), Some("flip"));
+/// ```
+pub fn expectimax_search<G>(game: &G, depth: usize) -> Option<G::Move>
+where
+    G: HeuristicGameTree,
+    G::Score: WholeScore,
+{
+    Expectimax::new().search(game, depth)
+}
+
+/// Settings that change how expectimax goes about its search.
+#[derive(Clone, Debug)]
+pub struct ExpectimaxConfig<S = isize> {
+    /// The lowest and highest score of any position: every heuristic, and `-Score::WIN` and
+    /// `Score::WIN` if the game can be won or lost. Pruning is only right if the bounds hold.
+    pub score_bounds: (S, S),
+    /// Skip the remaining events of a chance node once the events searched so far decide it
+    /// (Star1).
+    pub star1: bool,
+    /// Before searching the events of a chance node, search only the first move after each of
+    /// them, which is often enough to decide the chance node (Star2). Needs `star1`.
+    pub star2: bool,
+}
+
+impl<S: Score> Default for ExpectimaxConfig<S> {
+    fn default() -> Self {
+        ExpectimaxConfig {
+            score_bounds: (-S::WIN, S::WIN),
+            star1: true,
+            star2: true,
+        }
+    }
+}
+
+/// An expectimax searcher, for games with scores of type `S`.
+pub struct Expectimax<S = isize> {
+    config: ExpectimaxConfig<S>,
+    nodes: u64,
+    cutoffs: u64,
+}
+
+impl<S: WholeScore> Default for Expectimax<S> {
+    fn default() -> Self {
+        Expectimax::new()
+    }
+}
+
+impl<S: WholeScore> Expectimax<S> {
+    /// Creates a searcher with the default settings.
+    pub fn new() -> Self {
+        Expectimax {
+            config: ExpectimaxConfig::default(),
+            nodes: 0,
+            cutoffs: 0,
+        }
+    }
+
+    /// Replaces the settings of the search.
+    pub fn with_config(mut self, config: ExpectimaxConfig<S>) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// The number of positions visited by the last search.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Same as `expectimax_search`, but with the settings of this searcher.
+    pub fn search<G: HeuristicGameTree<Score = S>>(
+        &mut self,
+        game: &G,
+        depth: usize,
+    ) -> Option<G::Move> {
+        self.analyze(game, depth).best_move
+    }
+
+    /// Same as `search`, but returns a SearchResult, whose score is the expected heuristic of
+    /// the best move, rounded to the nearest whole number. Random events make the line of play
+    /// unknowable, so the principal variation only holds the best move.
+    pub fn analyze<G: HeuristicGameTree<Score = S>>(
+        &mut self,
+        game: &G,
+        depth: usize,
+    ) -> SearchResult<G::Move, S> {
+        let started = Instant::now();
+        self.nodes = 0;
+        self.cutoffs = 0;
+        let player = game.to_move();
+        let (best_move, value) = if depth == 0 {
+            // Choose first available move.
+            (
+                game.possible_moves().next(),
+                Expected::whole(evaluate(game, 0)),
+            )
+        } else if !Self::chances(game).is_empty() {
+            (
+                None,
+                self.value(game, depth, 0, -Expected::infinity(), Expected::infinity()),
+            )
+        } else {
+            self.root(game, depth)
+        };
+        SearchResult {
+            principal_variation: best_move.iter().cloned().collect(),
+            best_move,
+            score: player.perspective(value.round()),
+            depth,
+            nodes: self.nodes,
+            cutoffs: self.cutoffs,
+            elapsed: started.elapsed(),
+            ranked_moves: Vec::new(),
+        }
+    }
+
+    // Returns the move with the best expected heuristic, together with that heuristic
+    fn root<G: HeuristicGameTree<Score = S>>(
+        &mut self,
+        game: &G,
+        depth: usize,
+    ) -> (Option<G::Move>, Expected<S>) {
+        let player = game.to_move();
+        let (mut alpha, mut beta) = (-Expected::infinity(), Expected::infinity());
+        let mut best_move = None;
+        for mymove in game.possible_moves() {
+            let mut next_state = game.clone();
+            next_state.execute_move(&mymove);
+            let h = self.value(&next_state, depth - 1, 1, alpha, beta);
+            match player {
+                Player::Max if h > alpha => {
+                    alpha = h;
+                    best_move = Some(mymove);
+                }
+                Player::Min if h < beta => {
+                    beta = h;
+                    best_move = Some(mymove);
+                }
+                _ => {}
+            }
+        }
+        match (best_move, player) {
+            (None, _) => (None, Expected::whole(evaluate(game, 0))),
+            (best_move, Player::Max) => (best_move, alpha),
+            (best_move, Player::Min) => (best_move, beta),
+        }
+    }
+
+    // The expected heuristic of a position from Max's point of view, `ply` moves away from the
+    // root. Outside of the window between alpha and beta the result is only a bound (fail-soft).
+    fn value<G: HeuristicGameTree<Score = S>>(
+        &mut self,
+        game: &G,
+        depth: usize,
+        ply: usize,
+        mut alpha: Expected<S>,
+        mut beta: Expected<S>,
+    ) -> Expected<S> {
+        self.nodes += 1;
+        if game.outcome() != Outcome::Ongoing {
+            return Expected::whole(evaluate(game, ply));
+        }
+        // Random events do not count towards the depth, so a chance node is never a leaf
+        let chances = Self::chances(game);
+        if !chances.is_empty() {
+            return self.chance_value(game, chances, depth, ply, alpha, beta);
+        }
+        if depth == 0 {
+            return Expected::whole(game.heuristic());
+        }
+        let player = game.to_move();
+        let mut best = None;
+        for mymove in game.possible_moves() {
+            let mut next_state = game.clone();
+            next_state.execute_move(&mymove);
+            let h = self.value(&next_state, depth - 1, ply + 1, alpha, beta);
+            best = match (best, player) {
+                (Some(best), Player::Max) => Some(h.max(best)),
+                (Some(best), Player::Min) => Some(h.min(best)),
+                (None, _) => Some(h),
+            };
+            match player {
+                Player::Max => alpha = alpha.max(h),
+                Player::Min => beta = beta.min(h),
+            }
+            if beta <= alpha {
+                self.cutoffs += 1;
+                break;
+            }
+        }
+        // No possible moves, the position speaks for itself
+        best.unwrap_or_else(|| Expected::whole(game.heuristic()))
+    }
+
+    // The weighted average of the events of a chance node. Every event starts out known to lie
+    // within the score bounds, and the bounds narrow as events are searched, until the average
+    // is known, or is known to lie outside the window
+    fn chance_value<G: HeuristicGameTree<Score = S>>(
+        &mut self,
+        game: &G,
+        chances: Vec<(G::Move, u32)>,
+        depth: usize,
+        ply: usize,
+        alpha: Expected<S>,
+        beta: Expected<S>,
+    ) -> Expected<S> {
+        let total: i128 = chances.iter().map(|(_, weight)| *weight as i128).sum();
+        let odds: Vec<Expected<S>> = chances
+            .iter()
+            .map(|(_, weight)| Expected::fraction(*weight as i128, total))
+            .collect();
+        let children: Vec<G> = chances
+            .iter()
+            .map(|(event, _)| {
+                let mut child = game.clone();
+                child.execute_move(event);
+                child
+            })
+            .collect();
+        if !self.config.star1 {
+            let infinity = Expected::infinity();
+            let mut sum = Expected::whole(S::ZERO);
+            for (child, odds) in children.iter().zip(&odds) {
+                sum = sum + *odds * self.value(child, depth, ply, -infinity, infinity);
+            }
+            return sum;
+        }
+        let (low, high) = self.config.score_bounds;
+        let mut bounds = vec![(Expected::whole(low), Expected::whole(high)); children.len()];
+        if self.config.star2 {
+            for i in 0..children.len() {
+                let (child_alpha, child_beta) = child_window(&odds, &bounds, i, alpha, beta);
+                if let Some(bound) = self.probe(&children[i], depth, ply, child_alpha, child_beta) {
+                    bounds[i] = match children[i].to_move() {
+                        Player::Max => (bound.max(bounds[i].0), bounds[i].1),
+                        Player::Min => (bounds[i].0, bound.min(bounds[i].1)),
+                    };
+                }
+                if let Some(cutoff) = self.chance_cutoff(&odds, &bounds, alpha, beta) {
+                    return cutoff;
+                }
+            }
+        }
+        for i in 0..children.len() {
+            let (child_alpha, child_beta) = child_window(&odds, &bounds, i, alpha, beta);
+            let h = self.value(&children[i], depth, ply, child_alpha, child_beta);
+            bounds[i] = if h <= child_alpha {
+                (bounds[i].0, h)
+            } else if h >= child_beta {
+                (h, bounds[i].1)
+            } else {
+                (h, h)
+            };
+            if let Some(cutoff) = self.chance_cutoff(&odds, &bounds, alpha, beta) {
+                return cutoff;
+            }
+        }
+        weighted(&odds, &bounds, |b| b.0)
+    }
+
+    // A bound on a player's turn from searching only its first move: at least the first move's
+    // heuristic for Max, and at most that for Min, as long as the search of the move did not
+    // fail the wrong way. None if the position is not a player's turn.
+    fn probe<G: HeuristicGameTree<Score = S>>(
+        &mut self,
+        game: &G,
+        depth: usize,
+        ply: usize,
+        alpha: Expected<S>,
+        beta: Expected<S>,
+    ) -> Option<Expected<S>> {
+        if depth == 0 || game.outcome() != Outcome::Ongoing || !Self::chances(game).is_empty() {
+            return None;
+        }
+        let first = game.possible_moves().next()?;
+        let mut next_state = game.clone();
+        next_state.execute_move(&first);
+        let h = self.value(&next_state, depth - 1, ply + 1, alpha, beta);
+        match game.to_move() {
+            Player::Max if h > alpha => Some(h),
+            Player::Min if h < beta => Some(h),
+            _ => None,
+        }
+    }
+
+    // The bound to return if the events searched so far already put the average outside the window
+    fn chance_cutoff(
+        &mut self,
+        odds: &[Expected<S>],
+        bounds: &[Bounds<S>],
+        alpha: Expected<S>,
+        beta: Expected<S>,
+    ) -> Option<Expected<S>> {
+        let highest = weighted(odds, bounds, |b| b.1);
+        let lowest = weighted(odds, bounds, |b| b.0);
+        if highest <= alpha {
+            self.cutoffs += 1;
+            Some(highest)
+        } else if lowest >= beta {
+            self.cutoffs += 1;
+            Some(lowest)
+        } else {
+            None
+        }
+    }
+
+    // The events of a chance node that can happen
+    fn chances<G: HeuristicGameTree<Score = S>>(game: &G) -> Vec<(G::Move, u32)> {
+        let mut chances = game.chance_outcomes();
+        chances.retain(|(_, weight)| *weight > 0);
+        chances
+    }
+}
+
+// The window for event `i`, outside of which the average is decided whatever the other events
+// turn out to be
+fn child_window<S: WholeScore>(
+    odds: &[Expected<S>],
+    bounds: &[Bounds<S>],
+    i: usize,
+    alpha: Expected<S>,
+    beta: Expected<S>,
+) -> (Expected<S>, Expected<S>) {
+    let others_high = weighted(odds, bounds, |b| b.1) - odds[i] * bounds[i].1;
+    let others_low = weighted(odds, bounds, |b| b.0) - odds[i] * bounds[i].0;
+    let clamp = |h: Expected<S>| h.clamp(-Expected::infinity(), Expected::infinity());
+    (
+        clamp((alpha - others_high) / odds[i]),
+        clamp((beta - others_low) / odds[i]),
+    )
+}
+
+// The average of one side of the bounds of every event, weighted by its odds
+fn weighted<S: WholeScore>(
+    odds: &[Expected<S>],
+    bounds: &[Bounds<S>],
+    side: fn(&Bounds<S>) -> Expected<S>,
+) -> Expected<S> {
+    odds.iter()
+        .zip(bounds)
+        .fold(Expected::whole(S::ZERO), |sum, (odds, b)| {
+            sum + *odds * side(b)
+        })
+}
+
+// What is known of an event: the lowest and the highest its expected heuristic can be
+type Bounds<S> = (Expected<S>, Expected<S>);
+
+// The largest denominator an Expected keeps, past which it is rounded to a multiple of its
+// inverse. Only a long run of chance nodes with uneven weights gets there.
+const MAX_DENOMINATOR: i128 = 1 << 24;
+
+// An expected heuristic, or the odds of an event: an exact fraction of widened scores (see
+// `WholeScore`), so that averages do not round close choices into ties. The fraction is kept in
+// lowest terms with a positive denominator, so equal values have equal parts. Arithmetic that
+// would overflow falls back on floating point, rounded to MAX_DENOMINATOR.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+struct Expected<S> {
+    numerator: i128,
+    denominator: i128,
+    score: PhantomData<S>,
+}
+
+impl<S: WholeScore> Expected<S> {
+    fn new(numerator: i128, denominator: i128) -> Self {
+        Expected {
+            numerator,
+            denominator,
+            score: PhantomData,
+        }
+    }
+
+    fn infinity() -> Self {
+        Expected::whole(S::INFINITY)
+    }
+
+    fn whole(h: S) -> Self {
+        Expected::new(h.widen(), 1)
+    }
+
+    fn fraction(numerator: i128, denominator: i128) -> Self {
+        Expected::exact(Some(numerator), Some(denominator))
+            .unwrap_or_else(|| Expected::approximate(numerator as f64 / denominator as f64))
+    }
+
+    // The fraction in lowest terms, or None if a part overflowed
+    fn exact(numerator: Option<i128>, denominator: Option<i128>) -> Option<Self> {
+        let (mut numerator, mut denominator) = (numerator?, denominator?);
+        if denominator < 0 {
+            numerator = numerator.checked_neg()?;
+            denominator = denominator.checked_neg()?;
+        }
+        let divisor = gcd(numerator, denominator).max(1);
+        let (numerator, denominator) = (numerator / divisor, denominator / divisor);
+        if denominator <= MAX_DENOMINATOR {
+            return Some(Expected::new(numerator, denominator));
+        }
+        // Round to the nearest multiple of 1 / MAX_DENOMINATOR
+        let whole = numerator.div_euclid(denominator);
+        let part = (numerator
+            .rem_euclid(denominator)
+            .checked_mul(2 * MAX_DENOMINATOR)?
+            + denominator)
+            / (2 * denominator);
+        Expected::exact(
+            whole.checked_mul(MAX_DENOMINATOR)?.checked_add(part),
+            Some(MAX_DENOMINATOR),
+        )
+    }
+
+    fn approximate(value: f64) -> Self {
+        let infinity = Self::infinity();
+        let value = value.clamp(-infinity.to_f64(), infinity.to_f64());
+        let rounded = Expected::fraction(
+            (value * MAX_DENOMINATOR as f64).round() as i128,
+            MAX_DENOMINATOR,
+        );
+        rounded.clamp(-infinity, infinity)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    // The nearest score, halves rounded up
+    fn round(self) -> S {
+        let doubled = self
+            .numerator
+            .saturating_mul(2)
+            .saturating_add(self.denominator);
+        let infinity = S::INFINITY.widen();
+        S::narrow(
+            doubled
+                .div_euclid(2 * self.denominator)
+                .clamp(-infinity, infinity),
+        )
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i128
+}
+
+impl<S: WholeScore> Ord for Expected<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (
+            self.numerator.checked_mul(other.denominator),
+            other.numerator.checked_mul(self.denominator),
+        ) {
+            (Some(left), Some(right)) => left.cmp(&right),
+            _ => self.to_f64().total_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl<S: WholeScore> PartialOrd for Expected<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: WholeScore> Neg for Expected<S> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Expected::new(-self.numerator, self.denominator)
+    }
+}
+
+impl<S: WholeScore> Add for Expected<S> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)
+            .zip(other.numerator.checked_mul(self.denominator))
+            .and_then(|(a, b)| a.checked_add(b));
+        let denominator = self.denominator.checked_mul(other.denominator);
+        Expected::exact(numerator, denominator)
+            .unwrap_or_else(|| Expected::approximate(self.to_f64() + other.to_f64()))
+    }
+}
+
+impl<S: WholeScore> Sub for Expected<S> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl<S: WholeScore> Mul for Expected<S> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        let numerator = self.numerator.checked_mul(other.numerator);
+        let denominator = self.denominator.checked_mul(other.denominator);
+        Expected::exact(numerator, denominator)
+            .unwrap_or_else(|| Expected::approximate(self.to_f64() * other.to_f64()))
+    }
+}
+
+impl<S: WholeScore> Div for Expected<S> {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        let numerator = self.numerator.checked_mul(other.denominator);
+        let denominator = self.denominator.checked_mul(other.numerator);
+        Expected::exact(numerator, denominator)
+            .unwrap_or_else(|| Expected::approximate(self.to_f64() / other.to_f64()))
+    }
+}
+
+#[cfg(test)]
+mod expectimax_tests {
+    use super::{expectimax_search, Expected, Expectimax, ExpectimaxConfig, MAX_DENOMINATOR};
+    use crate::{HeuristicGameTree, Player};
+
+    type Average = Expected<isize>;
+
+    // Max either takes a sure point, or bets on a coin that pays 4 on a win and costs 3 on a loss
+    #[derive(Clone)]
+    struct Bet {
+        odds: (u32, u32),
+        stage: usize,
+        points: isize,
+    }
+
+    const TAKE: usize = 0;
+    const BET: usize = 1;
+    const WON: usize = 2;
+    const LOST: usize = 3;
+
+    impl HeuristicGameTree for Bet {
+        type Move = usize;
+        type Score = isize;
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            if self.stage == 0 {
+                Box::new(vec![TAKE, BET].into_iter())
+            } else {
+                Box::new(std::iter::empty())
+            }
+        }
+        fn heuristic(&self) -> isize {
+            self.points
+        }
+        fn execute_move(&mut self, next_move: &Self::Move) {
+            self.stage += 1;
+            self.points += match *next_move {
+                TAKE => 1,
+                WON => 4,
+                LOST => -3,
+                _ => 0,
+            };
+        }
+        fn to_move(&self) -> Player {
+            Player::Max
+        }
+        fn chance_outcomes(&self) -> Vec<(Self::Move, u32)> {
+            if self.stage == 1 && self.points == 0 {
+                vec![(LOST, self.odds.1), (WON, self.odds.0)]
+            } else {
+                Vec::new()
+            }
+        }
+    }
+
+    fn bet(odds: (u32, u32)) -> Bet {
+        Bet {
+            odds,
+            stage: 0,
+            points: 0,
+        }
+    }
+
+    // Every turn a die with faces 1 to 3 is rolled, then the player to move either moves their
+    // counter ahead by the roll, or moves the other player's counter back by one
+    #[derive(Clone)]
+    struct DiceRace {
+        turns: usize,
+        roll: Option<isize>,
+        to_move: Player,
+        lead: isize,
+    }
+
+    const AHEAD: usize = 10;
+    const BACK: usize = 11;
+
+    impl HeuristicGameTree for DiceRace {
+        type Move = usize;
+        type Score = isize;
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            match (self.roll, self.turns) {
+                (Some(_), _) => Box::new(vec![AHEAD, BACK].into_iter()),
+                (None, 0) => Box::new(std::iter::empty()),
+                (None, _) => Box::new((1..=3).map(|roll| roll as usize)),
+            }
+        }
+        fn heuristic(&self) -> isize {
+            // Only who is ahead counts
+            self.lead.signum()
+        }
+        fn execute_move(&mut self, next_move: &Self::Move) {
+            match (self.roll, *next_move) {
+                (None, roll) => self.roll = Some(roll as isize),
+                (Some(roll), choice) => {
+                    let gain = if choice == AHEAD { roll } else { 1 };
+                    self.lead += self.to_move.sign() * gain;
+                    self.roll = None;
+                    self.turns -= 1;
+                    self.to_move = self.to_move.opponent();
+                }
+            }
+        }
+        fn to_move(&self) -> Player {
+            self.to_move
+        }
+        fn chance_outcomes(&self) -> Vec<(Self::Move, u32)> {
+            match (self.roll, self.turns) {
+                (None, 1..) => vec![(1, 1), (2, 1), (3, 2)],
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    fn race(turns: usize) -> DiceRace {
+        DiceRace {
+            turns,
+            roll: Some(3),
+            to_move: Player::Max,
+            lead: 0,
+        }
+    }
+
+    #[test]
+    fn expected_value_test() {
+        // Three wins for every loss make the bet worth 2.25 points, one in four makes it -1.25
+        assert_eq!(expectimax_search(&bet((3, 1)), 2), Some(BET));
+        assert_eq!(expectimax_search(&bet((1, 3)), 2), Some(TAKE));
+        // Two wins for every loss make it worth 1.67, a little more than the sure point
+        assert_eq!(expectimax_search(&bet((2, 1)), 2), Some(BET));
+        let result = Expectimax::new().analyze(&bet((2, 1)), 2);
+        assert_eq!(result.score, 2);
+        // Four wins for every three losses make it worth 1
+        assert_eq!(expectimax_search(&bet((4, 3)), 2), Some(TAKE));
+    }
+
+    #[test]
+    fn expected_arithmetic_test() {
+        let third = Average::fraction(1, 3);
+        assert_eq!(third + third + third, Average::whole(1));
+        assert!(Average::fraction(2, 3) < Average::fraction(3, 4));
+        assert_eq!(Average::fraction(-6, -4), Average::fraction(3, 2));
+        assert_eq!((Average::whole(1) / third).round(), 3);
+        assert_eq!(Average::fraction(-5, 4).round(), -1);
+        assert_eq!(Average::fraction(5, 2).round(), 3);
+        // Denominators past the limit are rounded
+        let tiny = Average::fraction(1, 3 * MAX_DENOMINATOR);
+        assert_eq!(tiny, Average::whole(0));
+        // Overflow falls back on floating point, which stays within the infinities
+        let huge = Average::fraction(i128::MAX, 1);
+        assert_eq!(huge * Average::whole(2), Average::approximate(f64::MAX));
+        assert!(huge * Average::whole(2) <= Average::infinity());
+    }
+
+    // The bet with i16 scores. Five wins for every three losses make it worth 1.375 points
+    #[derive(Clone)]
+    struct SmallBet(Bet);
+
+    impl HeuristicGameTree for SmallBet {
+        type Move = usize;
+        type Score = i16;
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            self.0.possible_moves()
+        }
+        fn heuristic(&self) -> i16 {
+            self.0.heuristic() as i16
+        }
+        fn execute_move(&mut self, next_move: &Self::Move) {
+            self.0.execute_move(next_move)
+        }
+        fn to_move(&self) -> Player {
+            self.0.to_move()
+        }
+        fn chance_outcomes(&self) -> Vec<(Self::Move, u32)> {
+            self.0.chance_outcomes()
+        }
+    }
+
+    #[test]
+    fn narrow_score_test() {
+        let game = SmallBet(bet((5, 3)));
+        assert_eq!(expectimax_search(&game, 2), Some(BET));
+        let mut searcher = Expectimax::new().with_config(ExpectimaxConfig {
+            score_bounds: (-3, 4),
+            ..ExpectimaxConfig::default()
+        });
+        let result = searcher.analyze(&game, 2);
+        assert_eq!((result.best_move, result.score), (Some(BET), 1i16));
+        assert_eq!(Expected::<i16>::fraction(1 << 20, 1).round(), i16::MAX);
+    }
+
+    #[test]
+    fn zero_weight_test() {
+        assert_eq!(expectimax_search(&bet((1, 0)), 2), Some(BET));
+        assert_eq!(expectimax_search(&bet((0, 1)), 2), Some(TAKE));
+    }
+
+    #[test]
+    fn pruning_matches_plain_test() {
+        let bounds = (-1, 1);
+        let plain = ExpectimaxConfig {
+            score_bounds: bounds,
+            star1: false,
+            star2: false,
+        };
+        let star1 = ExpectimaxConfig {
+            star2: false,
+            ..plain.clone()
+        };
+        let star2 = ExpectimaxConfig {
+            star1: true,
+            star2: true,
+            ..plain.clone()
+        };
+        for turns in 1..6 {
+            let mut searchers = [plain.clone(), star1.clone(), star2.clone()]
+                .map(|config| Expectimax::new().with_config(config));
+            let results: Vec<_> = searchers
+                .iter_mut()
+                .map(|searcher| searcher.analyze(&race(turns), turns))
+                .collect();
+            assert_eq!(results[0].score, results[1].score);
+            assert_eq!(results[0].score, results[2].score);
+            assert!(results[1].nodes <= results[0].nodes);
+        }
+    }
+
+    #[test]
+    fn star1_prunes_test() {
+        let plain = ExpectimaxConfig {
+            score_bounds: (-3, 4),
+            star1: false,
+            star2: false,
+        };
+        let star1 = ExpectimaxConfig {
+            star1: true,
+            ..plain.clone()
+        };
+        let mut plain = Expectimax::new().with_config(plain);
+        let mut star1 = Expectimax::new().with_config(star1);
+        assert_eq!(plain.search(&bet((1, 3)), 2), Some(TAKE));
+        assert_eq!(star1.search(&bet((1, 3)), 2), Some(TAKE));
+        // Once the bet is lost three times in four, winning the rest cannot beat the sure point
+        assert_eq!(star1.nodes(), plain.nodes() - 1);
+    }
+
+    #[test]
+    fn chance_root_test() {
+        let mut game = race(2);
+        game.execute_move(&BACK);
+        let result = Expectimax::new().analyze(&game, 2);
+        assert!(result.best_move.is_none());
+        assert!(result.nodes > 1);
+    }
+}
@@ -1,6 +1,22 @@
-use crate::checkers::start_checkers;
-use crate::connect4::start_con;
-use crate::tictactoe::start_tic;
+use crate::book::OpeningBook;
+use crate::checkers::{book_checkers, start_checkers};
+use crate::connect4::{book_con, start_con};
+use crate::minimax::{RootPolicy, SearchConfig, Searcher};
+use crate::rng::Rng;
+use crate::tictactoe::{book_tic, start_tic};
+use crate::HeuristicGameTree;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// How the computer agent chooses its moves.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Engine {
+    /// Minimax search with alpha-beta pruning, which judges positions by the game's heuristic
+    Minimax,
+    /// Monte Carlo tree search, which judges moves by playing random games to the end
+    MonteCarlo,
+}
 
 /// Starts the game after the player runs the program with the selected game.
 ///
@@ -8,17 +24,109 @@ use crate::tictactoe::start_tic;
 /// # Arguments
 /// * `game` - A usize that holds the number corresponding to the game type
 /// * `diff` - A usize that holds the difficulty of the game, which is either the default setting
-///            (medium, corresponding to the number 2), or a difficulty setting that has been chosen
-///            by the player (easy 1, medium 2, hard 3).
+///   (medium, corresponding to the number 2), or a difficulty setting that has been chosen
+///   by the player (easy 1, medium 2, hard 3).
+/// * `engine` - An Engine that holds how the computer agent chooses its moves
+/// * `book` - The opening book the minimax engine plays its first moves from, see `build_book`;
+///   an empty book leaves every move to the search
 ///
-pub fn lets_play(game: usize, diff: usize) {
+pub fn lets_play(game: usize, diff: usize, engine: Engine, book: &OpeningBook) {
     match game {
-        1 => start_tic(diff),
-        2 => start_con(diff),
-        3 => start_checkers(diff),
+        1 => start_tic(diff, engine, book),
+        2 => start_con(diff, engine, book),
+        3 => start_checkers(diff, engine, book),
         _ => println!("error"),
     }
 }
+/// The longest the computer agent may think about a single move, when it plays with Monte Carlo
+/// tree search.
+///
+/// Harder difficulties search deeper, so they are given more time to do it in.
+/// # Arguments
+/// * `difficulty` - A usize that holds the difficulty of the game (easy 1, medium 2, hard 3).
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::general_game::think_time;
+/// # use std::time::Duration;
+/// assert_eq!(think_time(2), Duration::from_millis(1000));
+/// ```
+pub fn think_time(difficulty: usize) -> Duration {
+    Duration::from_millis(500 * difficulty as u64)
+}
+
+/// The deepest the computer agent's minimax search may go. The node budget stops it long before.
+pub(crate) const MAX_DEPTH: usize = 64;
+
+/// How many positions the computer agent's minimax search may visit for a single move.
+///
+/// A budget of positions rather than of time, or of depth, plays just as strong on every
+/// machine, and in every game. Every difficulty visits 20 times as many positions as the one below.
+/// # Arguments
+/// * `difficulty` - A usize that holds the difficulty of the game (easy 1, medium 2, hard 3).
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::general_game::node_budget;
+/// assert_eq!(node_budget(1), 1_000);
+/// assert_eq!(node_budget(3), 400_000);
+/// ```
+pub fn node_budget(difficulty: usize) -> u64 {
+    1_000 * 20u64.pow(difficulty.saturating_sub(1) as u32)
+}
+
+/// The minimax Searcher the computer agent plays with. It picks at random between moves that
+/// are equally good, seeded from the clock, so it does not play the same game every time.
+pub fn computer_searcher<G: HeuristicGameTree>() -> Searcher<G> {
+    let config = SearchConfig {
+        policy: RootPolicy::RandomBest,
+        ..SearchConfig::default()
+    };
+    Searcher::new()
+        .with_config(config)
+        .with_rng(Rng::from_clock())
+}
+
+/// How many plies of the opening the books of the built-in games take in.
+pub(crate) const BOOK_PLIES: usize = 6;
+
+/// Function: Builds the opening book of a built-in game by self-play, and saves it to a file
+/// that a session can be started with (see `lets_play`). The searches that build the book go
+/// deeper than the hard difficulty does during a game, so this takes a while for connect4 and
+/// checkers.
+/// # Arguments
+/// * `game` - A usize that holds the number corresponding to the game type
+/// * `games` - How many games to play against itself; more games put more openings in the book
+/// * `path` - The file to save the book to
+///
+/// Returns the number of positions in the book, or the error that kept it from being saved
+pub fn build_book(game: usize, games: usize, path: &Path) -> io::Result<usize> {
+    let book = match game {
+        1 => book_tic(games),
+        2 => book_con(games),
+        3 => book_checkers(games),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "no such game")),
+    };
+    book.save(path)?;
+    Ok(book.len())
+}
+
+/// The move the computer agent plays from its opening book, or None if it has to search.
+/// Easy does not use the book, so that its openings are as weak as the rest of its game.
+/// # Arguments
+/// * `difficulty` - A usize that holds the difficulty of the game (easy 1, medium 2, hard 3).
+pub(crate) fn book_move<G: HeuristicGameTree>(
+    book: &OpeningBook,
+    game: &G,
+    difficulty: usize,
+    rng: &mut Rng,
+) -> Option<G::Move> {
+    if difficulty <= 1 {
+        return None;
+    }
+    book.probe(game, rng)
+}
+
 #[derive(PartialEq, Copy, Clone, Debug)]
 /// Game pieces for a two-player game where all the pieces have equal value.
 ///
@@ -32,10 +140,15 @@ pub enum Piece {
 
 impl Piece {
     fn is_x(self) -> bool {
-        if let Piece::X = self {
-            true
+        matches!(self, Piece::X)
+    }
+
+    /// A number for each kind of piece, X is 0 and O is 1. Used to look up hashing keys.
+    pub fn index(self) -> usize {
+        if self.is_x() {
+            0
         } else {
-            false
+            1
         }
     }
 }
@@ -44,7 +157,7 @@ impl Piece {
 ///
 /// # Arguments
 /// * `item` - Of type Option<Piece>, where it is either a None, or it holds an 'X' or 'O' game
-/// Piece. If it is a Some(X) or Some(O), then the content is unwrapped and printed.
+///   Piece. If it is a Some(X) or Some(O), then the content is unwrapped and printed.
 ///
 /// Returns a reference to a string, which is the element that is printed on the board.
 ///
@@ -86,6 +199,12 @@ mod gen_game_tests {
         assert!(x.is_x());
     }
 
+    #[test]
+    fn index_check() {
+        assert_eq!(Piece::X.index(), 0);
+        assert_eq!(Piece::O.index(), 1);
+    }
+
     #[test]
     fn x_printcheck() {
         let x = Piece::X;
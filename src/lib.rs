@@ -1,8 +1,184 @@
+pub mod book;
 pub mod checkers;
+pub mod connect3;
 pub mod connect4;
+pub mod expectimax;
 pub mod general_game;
+pub mod mcts;
 pub mod minimax;
+pub mod multiplayer;
+pub mod pns;
+pub mod rng;
+pub mod tablebase;
+#[cfg(test)]
+mod test_games;
 pub mod tictactoe;
+pub mod zobrist;
+
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{Add, Neg, Sub};
+
+/// One of the two players of a game.
+///
+/// The heuristic of a game is always given from the point of view of Max: the higher the
+/// heuristic, the better for Max, and the lower, the better for Min. In the built-in games the
+/// computer agent plays Max.
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::Player;
+/// assert_eq!(Player::Max.opponent(), Player::Min);
+/// assert_eq!(Player::Min.sign() * 5, -5);
+/// assert_eq!(Player::Min.perspective(5), -5);
+/// ```
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum Player {
+    /// The player who wants the heuristic as high as possible
+    Max,
+    /// The player who wants the heuristic as low as possible
+    Min,
+}
+
+impl Player {
+    /// Returns the other player.
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::Max => Player::Min,
+            Player::Min => Player::Max,
+        }
+    }
+
+    /// Returns 1 for Max and -1 for Min, which turns a heuristic into a score from this
+    /// player's point of view.
+    pub fn sign(self) -> isize {
+        match self {
+            Player::Max => 1,
+            Player::Min => -1,
+        }
+    }
+
+    /// Returns the score as this player sees it: as it is for Max, and negated for Min.
+    pub fn perspective<S: Score>(self, score: S) -> S {
+        match self {
+            Player::Max => score,
+            Player::Min => -score,
+        }
+    }
+}
+
+/// The type of a heuristic, which the search compares, negates and adds up.
+///
+/// Scores are ordered from worst to best for Max, and negating a score gives the same score
+/// from the point of view of Min. The signed integers are scores, and a game can bring a type
+/// of its own, such as a fixed-point number, a pair compared lexicographically, or a score that
+/// tells a won endgame apart from a good position. Addition only needs to be exact for the
+/// search to be: the search adds STEP, multiples of it, and the configured margins.
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::Score;
+/// assert_eq!(i32::WIN - i32::steps(3), i32::MAX / 2 - 3);
+/// assert!(-i32::INFINITY < -i32::WIN);
+/// assert_eq!(i16::steps(40_000), i16::MAX);
+/// ```
+pub trait Score:
+    Copy
+    + Ord
+    + Debug
+    + Send
+    + Sync
+    + 'static
+    + Neg<Output = Self>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+{
+    /// The score of an even position, and of a drawn game
+    const ZERO: Self;
+    /// The smallest difference between two scores, which the search uses for windows that
+    /// only tell whether a score is higher than another
+    const STEP: Self;
+    /// The score of a game that is won on the spot. A game won a few plies ahead scores that
+    /// many steps less, so faster wins are preferred, and a lost game scores the negation.
+    /// Heuristics should stay more than a thousand steps away from it.
+    const WIN: Self;
+    /// Higher than any score, and at least twice WIN
+    const INFINITY: Self;
+
+    /// Returns `count` steps, the score WIN loses over `count` plies. A count too large for the
+    /// type gives the largest score instead of wrapping around.
+    fn steps(count: usize) -> Self;
+
+    /// Returns the score as a number, for weighing moves against each other (see
+    /// `minimax::RootPolicy::Softmax`). Only the differences between scores matter.
+    fn to_f64(self) -> f64;
+}
+
+/// A score that is a whole number, such as the signed integers or a fixed-point number counted
+/// in its smallest unit. Expectimax averages scores exactly, as fractions of whole numbers, so
+/// it only searches games with these scores.
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::WholeScore;
+/// assert_eq!(i16::MIN.widen(), -32768);
+/// assert_eq!(i16::narrow(40_000), i16::MAX);
+/// ```
+pub trait WholeScore: Score {
+    /// Returns the score as an i128, which holds every score without loss
+    fn widen(self) -> i128;
+
+    /// Returns the score nearest to a whole number, the largest or smallest score for a number
+    /// the type cannot hold
+    fn narrow(wide: i128) -> Self;
+}
+
+macro_rules! integer_score {
+    ($($t:ty),*) => {$(
+        impl Score for $t {
+            const ZERO: Self = 0;
+            const STEP: Self = 1;
+            const WIN: Self = <$t>::MAX / 2;
+            const INFINITY: Self = <$t>::MAX;
+
+            fn steps(count: usize) -> Self {
+                <$t>::try_from(count).unwrap_or(<$t>::MAX)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+
+        impl WholeScore for $t {
+            fn widen(self) -> i128 {
+                self as i128
+            }
+
+            fn narrow(wide: i128) -> Self {
+                <$t>::try_from(wide).unwrap_or(if wide < 0 { <$t>::MIN } else { <$t>::MAX })
+            }
+        }
+    )*};
+}
+
+integer_score!(i16, i32, i64, i128, isize);
+
+/// Whether a game is over, and how it ended.
+///
+/// Like the heuristic, an outcome is given from the point of view of Player::Max.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum Outcome {
+    /// The game is over and Max won
+    Win,
+    /// The game is over and Max lost
+    Loss,
+    /// The game is over and nobody won
+    Draw,
+    /// The game is still being played
+    Ongoing,
+}
 
 #[allow(unused)]
 /// A trait for the ability to make a game tree of possible moves with values of heuristic evaluations.
@@ -13,13 +189,24 @@ pub mod tictactoe;
 /// Using heuristics is a common way to measure progress in a game. When dealing with game trees,
 /// the heuristic function is generally referred to as the evaluation function, or the static
 /// evaluator. The static evaluation takes in a board position, and gives it a score.
-/// The higher the score, the better it is for you, the lower, the better for the opponent.
+/// The higher the score, the better it is for Player::Max, the lower, the better for Player::Min.
 ///
 /// # How can I implement HeuristicGameTree?
-/// HeuristicGameTree requires the possible_moves, heuristic, execute_move methods to be implemented.
+/// HeuristicGameTree requires the Move and Score types, and the possible_moves, heuristic, execute_move, to_move methods to be implemented.
+/// The outcome method is optional, but without it the search cannot tell a won game from a good position.
+/// The position_key method is optional, and lets the search remember positions it has seen before.
+/// The move_priority method is optional too, and helps the search try good moves first.
+/// The can_undo and undo_move methods are optional, and save the search from cloning the game
+/// for every move it looks at.
+/// The is_noisy method is optional, and keeps the search from judging a position in the middle
+/// of an exchange of pieces.
+/// The pass_move method is optional, and lets the search try passing to prove that a position
+/// is good enough without searching it in full.
+/// The chance_outcomes method is only needed for games with dice, cards or other random events,
+/// which are searched with expectimax.
 /// An simple example implementation for a connect4 game is:
 /// ```
-/// use heuristic_game_tree::HeuristicGameTree;
+/// use heuristic_game_tree::{HeuristicGameTree, Player};
 ///
 /// #[derive(PartialEq, Copy, Clone, Debug)]
 /// pub enum Piece {
@@ -35,9 +222,10 @@ pub mod tictactoe;
 ///}
 /// impl HeuristicGameTree for Connect4Game{
 ///     type Move = usize;
+///     type Score = isize;
 ///
 ///     // Returns a box that contains a pointer to an iterator of all the moves that can be made
-///     fn possible_moves(&self) -> Box<Iterator<Item = Self::Move> + '_> {
+///     fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
 ///        let mut list = Vec::new();
 ///        for i in 0..7 { list.push(i);}
 ///        Box::new(list.into_iter())
@@ -46,27 +234,111 @@ pub mod tictactoe;
 ///     // Returns the value of the heuristic that determines if the move is advantageous.
 ///     fn heuristic(&self) -> isize {4}
 ///
-///     // Returns whether the move has been made.
-///     fn execute_move(&mut self, next_move: &Self::Move, is_opponent: bool) -> bool{true}
+///     // Makes the move on the board.
+///     fn execute_move(&mut self, next_move: &Self::Move) {}
+///
+///     // Returns whose turn it is.
+///     fn to_move(&self) -> Player {Player::Max}
 /// }
 /// ```
-
 pub trait HeuristicGameTree: Clone {
-    type Move: Clone + Sized;
-    //type Heuristic: PartialOrd;
+    type Move: Clone + Eq + Hash + Sized;
+    /// The type of the heuristic, isize for most games
+    type Score: Score;
 
     /// All the possible moves that the computer agent and player can make based on the existing
     /// game board are determined with this function.
     /// Returns a box of an iterator, where all the items are possible moves
-    fn possible_moves(&self) -> Box<Iterator<Item = Self::Move> + '_>;
+    fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_>;
 
     /// The computation of heuristics differ with the type of game
-    /// This makes the individually unique heuristics into a value of type isize
-    // Should be a type Heuristic that can be any type that can be compared; doing isize for now
-    fn heuristic(&self) -> isize;
+    /// This makes the individually unique heuristics into a value of type Score
+    /// It should stay well away from Score::WIN, which the search keeps for won and lost games
+    fn heuristic(&self) -> Self::Score;
 
     /// After determining the best move to make, the computer agent will execute the move
-    /// The game is updated with the computer agent's move and returns true if the next player is the opponent
+    /// The game is updated with the move of the player to move, and the turn passes on
     /// The player is now free to make their turn
-    fn execute_move(&mut self, next_move: &Self::Move, is_opponent: bool) -> bool;
+    fn execute_move(&mut self, next_move: &Self::Move);
+
+    /// The player whose turn it is. The game decides this by itself, so after execute_move it is
+    /// usually the other player, but a game may give the same player another turn, as checkers
+    /// does in the middle of a multi-jump.
+    fn to_move(&self) -> Player;
+
+    /// Whether the game is over, and if so who won.
+    /// The search scores a finished game as a win or loss, preferring the fastest win and the
+    /// slowest loss, and only asks for the heuristic of positions where the game goes on.
+    /// A position with no possible moves that is still Ongoing is scored by its heuristic.
+    /// Returns Ongoing by default
+    fn outcome(&self) -> Outcome {
+        Outcome::Ongoing
+    }
+
+    /// A number that identifies the current position, so the search can recognise a position
+    /// it has already looked at, even when it was reached through a different order of moves.
+    /// Two different positions should be very unlikely to share a key. The key does not need
+    /// to tell whose turn it is, as the search takes care of that.
+    /// Returns None if the game does not provide keys, which turns off the transposition table
+    fn position_key(&self) -> Option<u64> {
+        None
+    }
+
+    /// A guess at how good a move is, made before searching it. The search tries moves with a
+    /// higher priority first, and the sooner it finds the best move the more of the tree it can
+    /// skip. The guess only needs to be cheap, not accurate: it never changes the result.
+    /// Returns 0 for every move by default, which keeps the order of possible_moves
+    fn move_priority(&self, _next_move: &Self::Move) -> isize {
+        0
+    }
+
+    /// Whether the game can take moves back with undo_move.
+    /// When it can, the search plays every move on one copy of the game and undoes it
+    /// afterwards, instead of cloning the game before every move.
+    /// Returns false by default
+    fn can_undo(&self) -> bool {
+        false
+    }
+
+    /// Takes back `last_move`, which must be the last move executed on the game, leaving the
+    /// game exactly as it was before, position_key and to_move included.
+    /// Only called when can_undo returns true, so a game that overrides one overrides both
+    /// Does nothing by default
+    fn undo_move(&mut self, _last_move: &Self::Move) {
+        debug_assert!(
+            !self.can_undo(),
+            "a game that can undo moves has to implement undo_move"
+        );
+    }
+
+    /// Whether a move changes the position so much that the heuristic cannot be trusted until
+    /// the dust has settled, such as a capture. When the search reaches its depth limit, it
+    /// keeps searching the noisy moves until a position without any is reached (quiescence
+    /// search). Noisy moves should run out quickly, as captures do when pieces run out.
+    /// Returns false by default, which ends the search right at the depth limit
+    fn is_noisy(&self, _next_move: &Self::Move) -> bool {
+        false
+    }
+
+    /// The move that passes the turn to the other player without changing anything else, for
+    /// games whose rules allow passing. It is never one of possible_moves: the search only
+    /// plays it for null-move pruning (see `minimax::SearchConfig::null_move`), and takes it
+    /// back with undo_move when the game can undo. A game should only pass when being the one
+    /// to move is an advantage, which is not true of a position where every move makes things
+    /// worse (zugzwang).
+    /// Returns None by default, as most games do not allow passing
+    fn pass_move(&self) -> Option<Self::Move> {
+        None
+    }
+
+    /// The random events that can happen next, such as the rolls of a die, each with a weight:
+    /// an event with twice the weight is twice as likely. A position with random events is a
+    /// chance node, where nobody gets to choose, and the chosen event is played with
+    /// execute_move like a move. Events with a weight of 0 never happen.
+    /// Only expectimax knows about chance nodes; minimax would treat the events as moves of
+    /// the player to move.
+    /// Returns no events by default, so that every position is a player's turn
+    fn chance_outcomes(&self) -> Vec<(Self::Move, u32)> {
+        Vec::new()
+    }
 }
@@ -1,14 +1,52 @@
 use std::io::{stdout, Write};
+use std::path::Path;
 use std::{env, process};
 
+use heuristic_game_tree::book::OpeningBook;
 use heuristic_game_tree::general_game;
+use heuristic_game_tree::general_game::Engine;
+
+// How many games an opening book is built from, unless told otherwise
+const BOOK_GAMES: usize = 16;
 
 fn main() -> std::io::Result<()> {
+    if env::args().nth(1).is_some_and(|command| command == "book") {
+        return build_book();
+    }
     //let (game_num, difficulty_num) = choose_game(read_input().0, read_input().1);
-    let game_num = choose_game(read_input().0);
-    let difficulty_num = difficulty_level(read_input().1);
+    let (game, difficulty, engine, book) = read_input();
+    let game_num = choose_game(game);
+    let difficulty_num = difficulty_level(difficulty);
+    let engine = choose_engine(engine);
+    let book = match book {
+        Some(path) => OpeningBook::load(path)?,
+        None => OpeningBook::new(),
+    };
+
+    general_game::lets_play(game_num, difficulty_num, engine, &book);
+    Ok(())
+}
 
-    general_game::lets_play(game_num, difficulty_num);
+/// Building an opening book for one of the games, to play with later:
+/// cargo run book checkers checkers.book
+///
+/// The book is built from 16 games the computer agent plays against itself, unless the number
+/// of games is given as last argument (e.g. cargo run book checkers checkers.book 64)
+fn build_book() -> std::io::Result<()> {
+    let game = choose_game(env::args().nth(2).unwrap_or_default());
+    let path = env::args().nth(3).unwrap_or_else(|| {
+        eprintln!(
+            "Error. Please provide a file to save the book to\nSyntax: book game file [games]"
+        );
+        process::exit(1);
+    });
+    let games = env::args()
+        .nth(4)
+        .and_then(|games| games.parse().ok())
+        .unwrap_or(BOOK_GAMES);
+    println!("Building the opening book from {} games ...", games);
+    let positions = general_game::build_book(game, games, Path::new(&path))?;
+    println!("Saved {} positions to {}", positions, path);
     Ok(())
 }
 
@@ -18,19 +56,23 @@ fn main() -> std::io::Result<()> {
 /// There are 3 different difficulty levels: easy, medium, hard
 /// User has option of setting difficulty level as second argument (e.g. cargo run checkers medium)
 /// Otherwise, default is medium
-
-fn read_input() -> (String, String) {
+///
+/// The computer agent uses minimax unless "mcts" is given as third argument
+/// (e.g. cargo run checkers hard mcts), which makes it use Monte Carlo tree search
+///
+/// Minimax plays its first moves from an opening book if its file is given as fourth argument
+/// (e.g. cargo run checkers hard minimax checkers.book), see build_book
+fn read_input() -> (String, String, String, Option<String>) {
     let game = env::args().nth(1).unwrap_or_else(|| {
         eprintln!(
             "Error. Please provide a proper game name and run again\nSyntax: game difficulty"
         );
         ::std::process::exit(1);
     });
-    let difficulty = "".to_owned();
-    if let Some(arg2) = env::args().nth(2) {
-        let _difficulty = arg2;
-    }
-    (game, difficulty)
+    let difficulty = env::args().nth(2).unwrap_or_default();
+    let engine = env::args().nth(3).unwrap_or_default();
+    let book = env::args().nth(4);
+    (game, difficulty, engine, book)
 }
 
 fn choose_game(game: String) -> usize {
@@ -63,6 +105,15 @@ fn difficulty_level(difficulty: String) -> usize {
     }
 }
 
+fn choose_engine(engine: String) -> Engine {
+    let engine = engine.to_lowercase();
+    if engine.contains("mcts") || engine.contains("monte") {
+        Engine::MonteCarlo
+    } else {
+        Engine::Minimax
+    }
+}
+
 #[cfg(test)]
 mod start_tests {
     use super::*;
@@ -97,6 +148,16 @@ mod start_tests {
         assert_eq!(difficulty_level("HARD".to_owned()), 3);
     }
 
+    #[test]
+    fn choose_engine_default() {
+        assert_eq!(choose_engine("".to_owned()), Engine::Minimax);
+    }
+
+    #[test]
+    fn choose_engine_mcts() {
+        assert_eq!(choose_engine("MCTS".to_owned()), Engine::MonteCarlo);
+    }
+
     #[test]
     fn choose_game_tic() {
         assert_eq!(choose_game("tic".to_owned()), 1);
@@ -0,0 +1,387 @@
+use super::{HeuristicGameTree, Outcome, Player, Score};
+use crate::rng::Rng;
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+/// Function: Monte Carlo tree search (UCT).
+/// Instead of judging positions by their heuristic, Monte Carlo tree search plays many games to
+/// the end with random moves (playouts), and prefers the moves whose playouts were won most
+/// often. Every playout also grows a tree of the positions it went through, and later playouts
+/// follow that tree to the moves that have done well so far, while still now and then trying
+/// moves that have hardly been played (the UCT formula). That makes it a good fit for games
+/// without a good heuristic.
+///
+/// The search relies on `HeuristicGameTree::outcome` to know who won a playout, so games
+/// should provide it. The heuristic is only used to judge playouts that are cut short.
+///
+/// # Arguments
+/// * `iterations` - A u64 that holds how many playouts to make
+///
+/// * `seed` - A u64 that decides the random moves; the same seed always gives the same move
+///
+/// Returns the move that was played in the most playouts
+///
+/// # Example
+/// Countdown is the game of the `Searcher` example (see `minimax::Searcher`).
+/// ```
+/// # use heuristic_game_tree::{HeuristicGameTree, Outcome, Player};
+/// # use heuristic_game_tree::mcts::mcts_search;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/doc/countdown.rs"));
+/// # fn main() {
+/// // Leave the other player a multiple of three
+/// assert_eq!(mcts_search(&Countdown(4, Player::Max), 1000, 7), Some(1));
+/// # }
+/// ```
+pub fn mcts_search<G>(game: &G, iterations: u64, seed: u64) -> Option<G::Move>
+where
+    G: HeuristicGameTree,
+{
+    Mcts::new(Rng::new(seed)).search(game, iterations)
+}
+
+/// Function: Monte Carlo tree search (UCT) under a wall-clock time budget.
+/// Same as `mcts_search`, but makes as many playouts as fit in the time budget.
+/// If the budget runs out before the first playout, the first available move is returned.
+///
+/// # Arguments
+/// * `budget` - A Duration that holds how long the computer agent may think about its move
+///
+/// * `seed` - A u64 that decides the random moves
+///
+/// Returns the move that was played in the most playouts
+pub fn mcts_search_timed<G>(game: &G, budget: Duration, seed: u64) -> Option<G::Move>
+where
+    G: HeuristicGameTree,
+{
+    Mcts::new(Rng::new(seed)).search_timed(game, budget)
+}
+
+/// Settings that change how Monte Carlo tree search goes about its search.
+#[derive(Clone, Debug)]
+pub struct MctsConfig {
+    /// How much the search favours moves it has hardly tried over moves that have won often so
+    /// far. Higher values spread the playouts more evenly. The usual choice is the square root of 2.
+    pub exploration: f64,
+    /// The most moves a playout makes. A playout that is cut short, or that runs out of moves
+    /// while the game goes on, counts as won by the player the heuristic favours.
+    pub playout_limit: usize,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            exploration: std::f64::consts::SQRT_2,
+            playout_limit: 200,
+        }
+    }
+}
+
+/// Everything a Monte Carlo tree search found out about a position.
+#[derive(Clone, Debug)]
+pub struct MctsResult<M> {
+    /// The move played in the most playouts, or None if there are no possible moves
+    pub best_move: Option<M>,
+    /// The share of the playouts through the best move that were won by the player to move,
+    /// from 0 (all lost) to 1 (all won), with drawn playouts counting half
+    pub win_rate: f64,
+    /// How many playouts went through the best move
+    pub visits: u64,
+    /// How many playouts the search made
+    pub iterations: u64,
+    /// How long the search took
+    pub elapsed: Duration,
+}
+
+/// A Monte Carlo tree searcher, which owns the random number generator for its playouts.
+///
+/// The tree is grown from scratch on every search, but the generator carries on from one search
+/// to the next, so a game played with one Mcts is reproducible from its seed.
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::mcts::{Mcts, MctsConfig};
+/// # use heuristic_game_tree::rng::Rng;
+/// let config = MctsConfig {
+///     playout_limit: 50,
+///     ..MctsConfig::default()
+/// };
+/// let mcts = Mcts::new(Rng::new(42)).with_config(config);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mcts {
+    config: MctsConfig,
+    rng: Rng,
+}
+
+// A position in the search tree
+struct Node<M> {
+    // The move that leads here from the parent, and the player who made it
+    last_move: Option<M>,
+    mover: Player,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // Moves of the position that have no child yet
+    untried: Vec<M>,
+    visits: u64,
+    // The playouts through here that were won by the mover, with draws counting half
+    wins: f64,
+}
+
+impl Mcts {
+    /// Creates a searcher that makes its random moves with `rng`.
+    pub fn new(rng: Rng) -> Self {
+        Mcts {
+            config: MctsConfig::default(),
+            rng,
+        }
+    }
+
+    /// Replaces the settings of the search.
+    pub fn with_config(mut self, config: MctsConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Same as `mcts_search`, but with the settings and generator of this searcher.
+    pub fn search<G: HeuristicGameTree>(&mut self, game: &G, iterations: u64) -> Option<G::Move> {
+        self.analyze(game, iterations).best_move
+    }
+
+    /// Same as `mcts_search_timed`, but with the settings and generator of this searcher.
+    pub fn search_timed<G: HeuristicGameTree>(
+        &mut self,
+        game: &G,
+        budget: Duration,
+    ) -> Option<G::Move> {
+        self.analyze_timed(game, budget).best_move
+    }
+
+    /// Same as `search`, but returns an MctsResult instead of only the best move.
+    pub fn analyze<G: HeuristicGameTree>(
+        &mut self,
+        game: &G,
+        iterations: u64,
+    ) -> MctsResult<G::Move> {
+        self.run(game, Some(iterations), None)
+    }
+
+    /// Same as `search_timed`, but returns an MctsResult instead of only the best move.
+    pub fn analyze_timed<G: HeuristicGameTree>(
+        &mut self,
+        game: &G,
+        budget: Duration,
+    ) -> MctsResult<G::Move> {
+        let started = Instant::now();
+        self.run(game, None, Some(started + budget))
+    }
+
+    fn run<G: HeuristicGameTree>(
+        &mut self,
+        game: &G,
+        iterations: Option<u64>,
+        deadline: Option<Instant>,
+    ) -> MctsResult<G::Move> {
+        let started = Instant::now();
+        let mut tree = vec![Node {
+            last_move: None,
+            mover: game.to_move().opponent(),
+            parent: None,
+            children: Vec::new(),
+            untried: Self::moves(game),
+            visits: 0,
+            wins: 0.0,
+        }];
+        let mut done = 0;
+        while iterations.is_none_or(|iterations| done < iterations)
+            && deadline.is_none_or(|deadline| Instant::now() < deadline)
+        {
+            self.iterate(game, &mut tree);
+            done += 1;
+        }
+        let best = tree[0]
+            .children
+            .iter()
+            .map(|child| &tree[*child])
+            .max_by_key(|child| child.visits);
+        match best {
+            Some(child) => MctsResult {
+                best_move: child.last_move.clone(),
+                win_rate: child.wins / child.visits as f64,
+                visits: child.visits,
+                iterations: done,
+                elapsed: started.elapsed(),
+            },
+            None => MctsResult {
+                // No playouts in time, choose first available move.
+                best_move: game.possible_moves().next(),
+                win_rate: 0.5,
+                visits: 0,
+                iterations: done,
+                elapsed: started.elapsed(),
+            },
+        }
+    }
+
+    // One playout: follow the tree down, add a position to it, play randomly from there to the
+    // end of the game, and let every position on the way know how it went
+    fn iterate<G: HeuristicGameTree>(&mut self, game: &G, tree: &mut Vec<Node<G::Move>>) {
+        let mut state = game.clone();
+        let mut node = 0;
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            node = self.select(tree, node);
+            state.execute_move(tree[node].last_move.as_ref().unwrap());
+        }
+        if !tree[node].untried.is_empty() {
+            let i = self.rng.below(tree[node].untried.len());
+            let next_move = tree[node].untried.swap_remove(i);
+            let mover = state.to_move();
+            state.execute_move(&next_move);
+            let child = tree.len();
+            tree.push(Node {
+                last_move: Some(next_move),
+                mover,
+                parent: Some(node),
+                children: Vec::new(),
+                untried: Self::moves(&state),
+                visits: 0,
+                wins: 0.0,
+            });
+            tree[node].children.push(child);
+            node = child;
+        }
+        let result = self.playout(state);
+        let mut current = Some(node);
+        while let Some(n) = current {
+            let n = &mut tree[n];
+            n.visits += 1;
+            n.wins += match n.mover {
+                Player::Max => result,
+                Player::Min => 1.0 - result,
+            };
+            current = n.parent;
+        }
+    }
+
+    // The child with the best balance between winning often and having been tried rarely
+    fn select<M>(&self, tree: &[Node<M>], node: usize) -> usize {
+        let log_visits = (tree[node].visits as f64).ln();
+        let uct = |child: usize| {
+            let child = &tree[child];
+            let visits = child.visits as f64;
+            child.wins / visits + self.config.exploration * (log_visits / visits).sqrt()
+        };
+        let mut children = tree[node].children.iter().copied();
+        let first = children.next().unwrap();
+        children
+            .fold((first, uct(first)), |best, child| {
+                let value = uct(child);
+                if value > best.1 {
+                    (child, value)
+                } else {
+                    best
+                }
+            })
+            .0
+    }
+
+    // Plays random moves until the game ends, and returns how it went for Max: 1 for a win,
+    // 0 for a loss and a half for a draw
+    fn playout<G: HeuristicGameTree>(&mut self, mut state: G) -> f64 {
+        for _ in 0..self.config.playout_limit {
+            let moves = Self::moves(&state);
+            if moves.is_empty() {
+                break;
+            }
+            state.execute_move(&moves[self.rng.below(moves.len())]);
+        }
+        match state.outcome() {
+            Outcome::Win => 1.0,
+            Outcome::Loss => 0.0,
+            Outcome::Draw => 0.5,
+            Outcome::Ongoing => match state.heuristic().cmp(&G::Score::ZERO) {
+                Ordering::Greater => 1.0,
+                Ordering::Less => 0.0,
+                Ordering::Equal => 0.5,
+            },
+        }
+    }
+
+    // The moves of a position, or none at all once the game is over
+    fn moves<G: HeuristicGameTree>(game: &G) -> Vec<G::Move> {
+        if game.outcome() == Outcome::Ongoing {
+            game.possible_moves().collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod mcts_tests {
+    use super::{mcts_search, mcts_search_timed, Mcts, MctsConfig};
+    use crate::rng::Rng;
+    use crate::test_games::{nim, Nim};
+    use crate::Player;
+    use std::time::Duration;
+
+    #[test]
+    fn finds_win_test() {
+        assert_eq!(mcts_search(&nim(3), 200, 1), Some(3));
+        assert_eq!(mcts_search(&nim(6), 3000, 1), Some(2));
+        let game = Nim {
+            pile: 5,
+            to_move: Player::Min,
+        };
+        assert_eq!(mcts_search(&game, 3000, 1), Some(1));
+    }
+
+    #[test]
+    fn same_seed_test() {
+        let mut a = Mcts::new(Rng::new(9));
+        let mut b = Mcts::new(Rng::new(9));
+        for pile in 4..12 {
+            let a = a.analyze(&nim(pile), 100);
+            let b = b.analyze(&nim(pile), 100);
+            assert_eq!(a.best_move, b.best_move);
+            assert_eq!(a.visits, b.visits);
+        }
+    }
+
+    #[test]
+    fn analyze_test() {
+        let result = Mcts::new(Rng::new(5)).analyze(&nim(5), 2000);
+        assert_eq!(result.best_move, Some(1));
+        assert_eq!(result.iterations, 2000);
+        assert!(result.visits > 1000);
+        assert!(result.win_rate > 0.5);
+    }
+
+    #[test]
+    fn playout_limit_test() {
+        let config = MctsConfig {
+            playout_limit: 0,
+            ..MctsConfig::default()
+        };
+        // Every playout is cut short and scored as a draw by the heuristic
+        let result = Mcts::new(Rng::new(5))
+            .with_config(config)
+            .analyze(&nim(20), 30);
+        assert!(result.best_move.is_some());
+        assert_eq!(result.win_rate, 0.5);
+    }
+
+    #[test]
+    fn timed_test() {
+        assert_eq!(
+            mcts_search_timed(&nim(7), Duration::from_secs(1), 3),
+            Some(3)
+        );
+        assert!(mcts_search_timed(&nim(7), Duration::from_secs(0), 3).is_some());
+    }
+
+    #[test]
+    fn no_moves_test() {
+        let result = Mcts::new(Rng::new(1)).analyze(&nim(0), 10);
+        assert!(result.best_move.is_none());
+    }
+}
@@ -1,5 +1,44 @@
-use super::HeuristicGameTree;
-use core::isize::{MAX, MIN};
+use super::{HeuristicGameTree, Outcome, Player, Score};
+use crate::rng::Rng;
+use crate::tablebase::Tablebase;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod table;
+
+pub use self::table::{Bound, TableEntry, TranspositionTable};
+
+// Number of nodes visited between two looks at the clock
+const CLOCK_CHECK_INTERVAL: u64 = 256;
+
+// Number of positions a Searcher remembers unless told otherwise
+const DEFAULT_TABLE_SIZE: usize = 1 << 16;
+
+// Mixed into position keys when it is Min's turn, so the same board with a different
+// player to move is not mistaken for the same position
+const MIN_TO_MOVE_KEY: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// How much shallower the position is searched after a pass in null-move pruning, on top of
+// the pass itself
+const NULL_MOVE_REDUCTION: usize = 2;
+
+// How many moves of a position are searched to the full depth before late-move reductions
+// start, and the least depth left for them to be worth it
+const FULL_DEPTH_MOVES: usize = 3;
+const REDUCTION_MIN_DEPTH: usize = 3;
+
+// The most plies a won or lost game can be from the root for its score to still tell it apart
+// from a heuristic
+pub(crate) const MAX_WIN_PLIES: usize = 1024;
+
+/// The score of a game that is won on the spot, for games with isize scores (see `Score::WIN`).
+/// A game won a few moves ahead scores `WIN_SCORE` less the number of moves, so faster wins
+/// are preferred, and a lost game scores the negation.
+pub const WIN_SCORE: isize = <isize as Score>::WIN;
 
 /// Function: Minimax with alpha-beta pruning.
 /// Minimax is a decision rule that minimizes the possible loss for a worst case (maximum loss) scenario,
@@ -8,20 +47,27 @@ use core::isize::{MAX, MIN};
 ///
 /// This function gets all the possible moves (i.e. children), then executes each move on a
 /// copy of the game. It gets the heuristic of each game copy executed on a next move by calling
-/// minimax recursively.
+/// minimax recursively. Games that can take moves back (see `HeuristicGameTree::can_undo`) are
+/// not copied: each move is executed, searched and undone on the same game.
+///
+/// The search is written as negamax: every position is scored from the point of view of the
+/// player to move, which is the heuristic for Player::Max and its negation for Player::Min.
+/// A player then always picks the move whose position is worst for the other player. When a
+/// move gives the same player another turn (as a multi-jump does in checkers) the score is
+/// passed up as it is.
 ///
 /// The game tree is cut off at a certain maximum depth, d (called a d-ply search), depending on the
 /// difficulty rating picked by the player. The heuristic function to those positions are at the
 /// bottom nodes of the tree, so instead of just Win, Loss, Tie, there is a heuristic score.
+/// Positions where `HeuristicGameTree::outcome` says the game is over are scored as a Win,
+/// Loss or Tie wherever they are found, with faster wins and slower losses preferred.
 ///
 /// # Arguments
 /// * `depth` - A usize that holds the depth of the minimax tree
-///             (it is the number of moves that the computer agent will plan ahead, and it
-///             represents the the difficulty of the game)
+///   (it is the number of moves that the computer agent will plan ahead, and it
+///   represents the the difficulty of the game)
 ///
-/// * `is_opponent` - A boolean that represents whether it is the player or computer's turn
-///
-/// Returns the best move (i.e. the move corresponding to the best heuristic)
+/// Returns the best move for the player to move (i.e. the move corresponding to the best heuristic)
 ///
 /// # Examples
 /// Basic usage:
@@ -47,14 +93,15 @@ use core::isize::{MAX, MIN};
 ///        }
 ///    }
 /// }
-/// # use heuristic_game_tree::HeuristicGameTree;
+/// # use heuristic_game_tree::{HeuristicGameTree, Player};
 /// // Implementing the HeuristicGameTree trait for the Connect4Game
 /// // since minimax_search takes in an argument `game` that has the trait HeuristicGameTree
 /// impl HeuristicGameTree for Connect4Game{
 ///     type Move = usize;
+///     type Score = isize;
 ///
 ///     // Returns a box that contains a pointer to an iterator of all the moves that can be made
-///     fn possible_moves(&self) -> Box<Iterator<Item = Self::Move> + '_> {
+///     fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
 ///        let mut list = Vec::new();
 ///        for i in 0..7 { list.push(i);}
 ///        Box::new(list.into_iter())
@@ -63,85 +110,2407 @@ use core::isize::{MAX, MIN};
 ///     // Returns the value of the heuristic that determines if the move is advantageous.
 ///     fn heuristic(&self) -> isize {4}
 ///
-///     // Returns whether the move has been made.
-///     fn execute_move(&mut self, next_move: &Self::Move, is_opponent: bool) -> bool{true}
+///     // Makes the move on the board.
+///     fn execute_move(&mut self, next_move: &Self::Move) {}
+///
+///     // Returns whose turn it is.
+///     fn to_move(&self) -> Player {Player::Max}
 /// }
 ///
 /// # use heuristic_game_tree::minimax::minimax_search;
-/// // creating the arguments for minimax_search(Connect4Game, medium difficulty, opponent's turn)
+/// // creating the arguments for minimax_search(Connect4Game, medium difficulty)
 /// let mut new_game = Connect4Game::new();
 /// let difficulty: usize = 2;
-/// let boolean = true;
 ///
 /// // next_move is an Option<usize>
 /// // since we are beginning with an empty Connect4Game board, the best move is the first move (i.e. 0)
-/// let next_move = minimax_search(&new_game, 3 * difficulty, boolean);
+/// let next_move = minimax_search(&new_game, 3 * difficulty);
 /// assert_eq!(0, next_move.unwrap());
 /// ```
-pub fn minimax_search<G>(game: &G, depth: usize, is_opponent: bool) -> Option<G::Move>
+pub fn minimax_search<G>(game: &G, depth: usize) -> Option<G::Move>
 where
     G: HeuristicGameTree,
 {
-    let mut best_move = (None, MIN); // We're going to maximize heuristic
-    if depth > 0 {
-        let mut alpha = MIN;
-        for mymove in game.possible_moves() {
-            let mut next_state = game.clone();
-            let opp = next_state.execute_move(&mymove, is_opponent); // Need to clone, standard procedure with minimax
-            let h = minimax_helper(&next_state, depth - 1, opp, alpha, MAX);
+    minimax_analyze(game, depth).best_move
+}
+
+/// Function: Minimax with alpha-beta pruning, reporting everything the search found out.
+/// Searches exactly like `minimax_search`, but instead of only the best move it returns a
+/// SearchResult, which also holds the heuristic of the best move, the line of play the search
+/// expects to follow, and how much work the search took.
+///
+/// # Arguments
+/// * `depth` - A usize that holds the depth of the minimax tree
+///
+/// Returns a SearchResult for the best move
+pub fn minimax_analyze<G>(game: &G, depth: usize) -> SearchResult<G::Move, G::Score>
+where
+    G: HeuristicGameTree,
+{
+    Searcher::new().analyze(game, depth)
+}
+
+/// Function: Same as `minimax_analyze`, but ranks the best `count` moves instead of finding only
+/// the best one (multi-PV), for hints and analysis. Alpha-beta only finds out that the other moves
+/// are no better than the best, so every move is searched against the `count`th best move found
+/// so far instead, which gives every move that makes the list an exact score.
+///
+/// # Arguments
+/// * `depth` - A usize that holds the depth of the minimax tree
+///
+/// * `count` - A usize that holds how many moves to rank
+///
+/// Returns a SearchResult whose ranked_moves hold the best `count` moves, best first
+pub fn minimax_analyze_multi_pv<G>(
+    game: &G,
+    depth: usize,
+    count: usize,
+) -> SearchResult<G::Move, G::Score>
+where
+    G: HeuristicGameTree,
+{
+    Searcher::new().analyze_multi_pv(game, depth, count)
+}
+
+/// Function: Minimax with iterative deepening under a wall-clock time budget.
+/// Searches the game tree to depth 1, then 2, then 3, and so on, until either `max_depth` has
+/// been searched, the whole game tree fits within the current depth, or the time budget runs out.
+///
+/// A search that is interrupted by the budget is thrown away, so the move returned always comes
+/// from the deepest search that completed. If not even the depth 1 search completes in time, the
+/// first available move is returned.
+///
+/// # Arguments
+/// * `max_depth` - A usize that holds the deepest the minimax tree is allowed to grow
+///
+/// * `budget` - A Duration that holds how long the computer agent may think about its move
+///
+/// Returns the best move of the deepest completed search
+pub fn minimax_search_timed<G>(game: &G, max_depth: usize, budget: Duration) -> Option<G::Move>
+where
+    G: HeuristicGameTree,
+{
+    minimax_analyze_timed(game, max_depth, budget).best_move
+}
+
+/// Function: Same as `minimax_search_timed`, but returns a SearchResult for the deepest
+/// completed search instead of only its best move.
+pub fn minimax_analyze_timed<G>(
+    game: &G,
+    max_depth: usize,
+    budget: Duration,
+) -> SearchResult<G::Move, G::Score>
+where
+    G: HeuristicGameTree,
+{
+    Searcher::new().analyze_timed(game, max_depth, budget)
+}
+
+/// Function: Minimax with iterative deepening under a budget of nodes.
+/// Same as `minimax_search_timed`, but the budget is the number of positions the search may
+/// visit rather than a time. The search visits the same positions in the same order every time,
+/// so it stops at the same place and chooses the same move on every machine, however fast.
+///
+/// # Arguments
+/// * `max_depth` - A usize that holds the deepest the minimax tree is allowed to grow
+///
+/// * `nodes` - A u64 that holds how many positions the search may visit
+///
+/// Returns the best move of the deepest completed search
+pub fn minimax_search_nodes<G>(game: &G, max_depth: usize, nodes: u64) -> Option<G::Move>
+where
+    G: HeuristicGameTree,
+{
+    minimax_analyze_nodes(game, max_depth, nodes).best_move
+}
+
+/// Function: Same as `minimax_search_nodes`, but returns a SearchResult for the deepest
+/// completed search instead of only its best move.
+pub fn minimax_analyze_nodes<G>(
+    game: &G,
+    max_depth: usize,
+    nodes: u64,
+) -> SearchResult<G::Move, G::Score>
+where
+    G: HeuristicGameTree,
+{
+    Searcher::new().analyze_nodes(game, max_depth, nodes)
+}
+
+/// Function: Beam search, minimax that only looks at the most promising moves of every position.
+/// For games with hundreds of moves a position, a full-width search cannot look more than a
+/// move or two ahead. Beam search only searches the `width` moves of every position that lead
+/// to the best heuristic for the player to move, with alpha-beta pruning and the usual move
+/// ordering as in `minimax_search`. The search reaches much deeper for the same work, but a
+/// move that only looks good after a few more moves is never found.
+///
+/// # Arguments
+/// * `depth` - A usize that holds the depth of the minimax tree
+///
+/// * `width` - A usize that holds how many moves of every position to search, at least 1
+///
+/// Returns the best move among the moves searched
+pub fn minimax_search_beam<G>(game: &G, depth: usize, width: usize) -> Option<G::Move>
+where
+    G: HeuristicGameTree,
+{
+    minimax_analyze_beam(game, depth, width).best_move
+}
+
+/// Function: Same as `minimax_search_beam`, but returns a SearchResult instead of only the
+/// best move. The score only takes the moves searched into account.
+pub fn minimax_analyze_beam<G>(
+    game: &G,
+    depth: usize,
+    width: usize,
+) -> SearchResult<G::Move, G::Score>
+where
+    G: HeuristicGameTree,
+{
+    Searcher::new().analyze_beam(game, depth, width)
+}
+
+/// Function: Minimax with alpha-beta pruning, searched on several threads at once.
+/// The moves of the current position are handed out to `threads` threads, each of which searches
+/// whole subtrees on its own. The best move scores the same as with `minimax_search`, but it can
+/// be a different move when several moves score the same.
+///
+/// # Arguments
+/// * `depth` - A usize that holds the depth of the minimax tree
+///
+/// * `threads` - A usize that holds how many threads to search with
+///
+/// Returns the best move for the player to move
+pub fn minimax_search_parallel<G>(game: &G, depth: usize, threads: usize) -> Option<G::Move>
+where
+    G: HeuristicGameTree + Send + Sync,
+    G::Move: Send + Sync,
+{
+    let config = SearchConfig {
+        threads,
+        ..SearchConfig::default()
+    };
+    Searcher::new()
+        .with_config(config)
+        .search_parallel(game, depth)
+}
+
+/// Function: Same as `minimax_search`, but finds the score with MTD(f): a series of null window
+/// searches that close in on the score from a first guess. Every search only tells whether the
+/// score is above or below its window, so it prunes much more than a search with a wide window,
+/// and the transposition table saves the next search from doing the same work again.
+///
+/// # Arguments
+/// * `depth` - A usize that holds the depth of the minimax tree
+///
+/// Returns the best move for the player to move
+pub fn minimax_search_mtdf<G>(game: &G, depth: usize) -> Option<G::Move>
+where
+    G: HeuristicGameTree,
+{
+    Searcher::new().search_mtdf(game, depth)
+}
+
+/// Everything a search found out about a position.
+#[derive(Clone, Debug)]
+pub struct SearchResult<M, S = isize> {
+    /// The move with the best heuristic, or None if there are no possible moves
+    pub best_move: Option<M>,
+    /// The heuristic the search expects to reach by playing the best move, from the point of
+    /// view of the player to move: higher is better for them, whether they are Max or Min.
+    /// Won and lost games score close to `Score::WIN` and its negation.
+    pub score: S,
+    /// The line of play the search expects, starting with the best move. It can be cut short
+    /// where the search reused a remembered position.
+    pub principal_variation: Vec<M>,
+    /// How many moves ahead the search looked: the deepest depth it completed, so a search
+    /// that was stopped part way through a depth reports the depth below
+    pub depth: usize,
+    /// How many positions the search visited
+    pub nodes: u64,
+    /// How many times alpha-beta pruning skipped the remaining moves of a position
+    pub cutoffs: u64,
+    /// How long the search took
+    pub elapsed: Duration,
+    /// The best moves, best first, each with its exact score and line of play. Only multi-PV
+    /// searches rank moves (see `minimax_analyze_multi_pv`), along with the random root policies,
+    /// which rank the moves they choose from (see RootPolicy). The others leave this empty.
+    pub ranked_moves: Vec<RankedMove<M, S>>,
+}
+
+/// A move ranked by a multi-PV search.
+#[derive(Clone, Debug)]
+pub struct RankedMove<M, S = isize> {
+    /// The move
+    pub next_move: M,
+    /// The exact heuristic the search expects to reach by playing the move, from the point of
+    /// view of the player to move, like `SearchResult::score`
+    pub score: S,
+    /// The line of play the search expects, starting with the move
+    pub principal_variation: Vec<M>,
+}
+
+/// How a Searcher chooses the move to play once it knows the scores of the root moves.
+///
+/// A search that always plays the best move, and the first of several equally good ones, plays
+/// the same game every time, which a player can learn to beat by rote. The random policies draw
+/// from the random number generator of the Searcher (see `Searcher::with_rng`), so a seeded
+/// Searcher still plays the same moves every time. Whatever the policy, `SearchResult::score`
+/// stays the score of the best move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RootPolicy {
+    /// The best move, and the first in move order when several moves are equally good
+    Best,
+    /// Any of the equally good best moves, each as likely. Moves that could tie with the best
+    /// are searched a little more to be sure of their score.
+    RandomBest,
+    /// Any move, the more likely the higher its score: a move that scores `temperature` less
+    /// than another is e (about 2.7) times less likely. A high temperature plays freely, a low
+    /// one keeps to the best moves. Every root move needs an exact score for this, as in a
+    /// multi-PV search, so the search prunes less, and the root is searched on a single thread.
+    Softmax { temperature: f64 },
+    /// Any possible move, each as likely, `epsilon` of the time (a fraction between 0 and 1),
+    /// and the best move the rest of the time
+    EpsilonGreedy { epsilon: f64 },
+}
+
+/// Settings that change how a Searcher goes about its search.
+///
+/// Most of them never change which heuristic the search finds for the best move, only how much
+/// work it takes to find it. The selective ones (null_move, late_move_reductions and
+/// futility_margin) trade a little certainty for depth, and the policy chooses the move to play.
+#[derive(Clone, Debug)]
+pub struct SearchConfig {
+    /// Try the most promising moves first: killer moves, then moves with a history of causing
+    /// cutoffs, then moves the game gives a high `HeuristicGameTree::move_priority`.
+    pub move_ordering: bool,
+    /// Search a single copy of the game, taking every move back with
+    /// `HeuristicGameTree::undo_move` once it has been searched, instead of cloning the game for
+    /// every move. Only used for games that `HeuristicGameTree::can_undo`.
+    pub undo_moves: bool,
+    /// Search on past the depth limit while the game has noisy moves to make, see
+    /// `HeuristicGameTree::is_noisy`. Only matters for games that mark moves as noisy.
+    pub quiescence: bool,
+    /// Use Principal Variation Search (also known as NegaScout): search the first move of every
+    /// position with the whole window, and the others with a null window that only proves they
+    /// are no better, searching them again only when they are. This pays off when the first move
+    /// is usually the best, which move ordering takes care of.
+    pub pvs: bool,
+    /// Start every depth of a timed search with a window of this size on either side of the score
+    /// of the previous depth (aspiration windows), instead of a window that takes in everything.
+    /// A narrow window prunes more, but when the score falls outside of it, the depth has to be
+    /// searched again with a wider window. A good size is about the worth of a piece in the
+    /// heuristic, counted in steps of the score (see `Score::STEP`). None turns aspiration
+    /// windows off.
+    pub aspiration_window: Option<usize>,
+    /// Null-move pruning: before searching a position, let the player to move pass, and search
+    /// the rest a few plies shallower. If the other player still cannot keep the score below
+    /// beta, a real move would do even better, and the position is not searched any further.
+    /// Only used for games that can pass, see `HeuristicGameTree::pass_move`.
+    pub null_move: bool,
+    /// Late-move reductions: moves that come late in the move order, and are not noisy, are
+    /// first searched one ply shallower, and only searched to the full depth when they turn out
+    /// better than the moves before them. Relies on good move ordering.
+    pub late_move_reductions: bool,
+    /// Futility pruning: one ply before the depth limit, quiet moves are skipped when the
+    /// heuristic of the position plus this margin still cannot reach alpha. The margin should
+    /// be the most a single quiet move can change the heuristic, counted in steps of the score
+    /// like the aspiration window. None turns futility pruning off.
+    pub futility_margin: Option<usize>,
+    /// How the move to play is chosen, see RootPolicy.
+    pub policy: RootPolicy,
+    /// How many threads the parallel searches of a Searcher use, such as `Searcher::search_parallel`.
+    /// Defaults to the number of threads the machine can run at once.
+    pub threads: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            move_ordering: true,
+            undo_moves: true,
+            quiescence: true,
+            pvs: true,
+            aspiration_window: None,
+            null_move: false,
+            late_move_reductions: false,
+            futility_margin: None,
+            policy: RootPolicy::Best,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+/// A minimax search that keeps what it learned between calls.
+///
+/// `minimax_search` and `minimax_search_timed` start from scratch on every move. A Searcher
+/// owns a transposition table instead, so positions searched while choosing one move are
+/// still remembered when choosing the next one. Games take advantage of the table by
+/// providing `HeuristicGameTree::position_key`. Keep one Searcher per game session.
+///
+/// The Searcher also remembers which moves caused alpha-beta cutoffs: the last two at every
+/// ply (killer moves), and a running score for every move (the history heuristic). Those moves
+/// are tried early, since a move that refuted one line often refutes its neighbours too, and
+/// the sooner the best move is found the more of the tree can be pruned.
+///
+/// # Example
+/// The examples of the search modules play Countdown: from a count, the players take 1 or 2
+/// steps in turn, and whoever takes the last step wins. Its HeuristicGameTree implementation
+/// is in `src/doc/countdown.rs`.
+/// ```
+/// # use heuristic_game_tree::{HeuristicGameTree, Outcome, Player};
+/// # use heuristic_game_tree::minimax::Searcher;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/doc/countdown.rs"));
+/// # fn main() {
+/// let mut searcher = Searcher::with_table_size(1024);
+/// // A multiple of three is lost for the player to move, so leave the other player one
+/// assert_eq!(searcher.search(&Countdown(7, Player::Max), 7), Some(1));
+/// assert_eq!(searcher.search(&Countdown(5, Player::Min), 5), Some(2));
+/// # }
+/// ```
+pub struct Searcher<G: HeuristicGameTree> {
+    config: SearchConfig,
+    table: TranspositionTable<G::Move, G::Score>,
+    killers: Vec<[Option<G::Move>; 2]>,
+    history: HashMap<G::Move, u64>,
+    ply: usize,
+    pass_ply: Option<usize>,
+    pv: Vec<Vec<G::Move>>,
+    multi_pv: usize,
+    beam_width: Option<usize>,
+    tablebase: Option<Arc<Tablebase>>,
+    ranked: Vec<RankedMove<G::Move, G::Score>>,
+    rng: Rng,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    stop: Arc<AtomicBool>,
+    progress: Option<ProgressCallback<G::Move, G::Score>>,
+    nodes: u64,
+    cutoffs: u64,
+    timed_out: bool,
+    hit_cutoff: bool,
+    helpers: Vec<Searcher<G>>,
+}
+
+// What ends an iterative deepening search before its maximum depth
+enum Limit {
+    Time(Duration),
+    Nodes(u64),
+}
+
+// Told about every depth a Searcher completes
+type ProgressCallback<M, S> = Box<dyn FnMut(&SearchResult<M, S>) + Send>;
+
+impl<G: HeuristicGameTree> Default for Searcher<G> {
+    fn default() -> Self {
+        Searcher::new()
+    }
+}
+
+impl<G: HeuristicGameTree> Searcher<G> {
+    /// Creates a Searcher with a transposition table of the default size.
+    pub fn new() -> Self {
+        Searcher::with_table_size(DEFAULT_TABLE_SIZE)
+    }
+
+    /// Creates a Searcher whose transposition table holds at most `size` positions.
+    /// A size of 0 turns the table off.
+    pub fn with_table_size(size: usize) -> Self {
+        Searcher {
+            config: SearchConfig::default(),
+            table: TranspositionTable::new(size),
+            killers: Vec::new(),
+            history: HashMap::new(),
+            ply: 0,
+            pass_ply: None,
+            pv: Vec::new(),
+            multi_pv: 1,
+            beam_width: None,
+            tablebase: None,
+            ranked: Vec::new(),
+            rng: Rng::new(0),
+            deadline: None,
+            node_limit: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            progress: None,
+            nodes: 0,
+            cutoffs: 0,
+            timed_out: false,
+            hit_cutoff: false,
+            helpers: Vec::new(),
+        }
+    }
+
+    /// Replaces the settings of the search.
+    pub fn with_config(mut self, config: SearchConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Gives the search a tablebase of positions whose outcome is known (see `Tablebase`).
+    /// Positions found in it are scored by their known outcome before the search looks at
+    /// their heuristic, or any deeper. Tablebases are large, so they are shared.
+    pub fn with_tablebase(mut self, tablebase: Arc<Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    /// Replaces the random number generator the random root policies draw from (see
+    /// RootPolicy). A new Searcher always starts from the same seed.
+    pub fn with_rng(mut self, rng: Rng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Calls `callback` every time the search completes a depth, with what it found out at that
+    /// depth, so that a frontend can show the search thinking. Searches to a fixed depth
+    /// complete a single depth, iterative deepening completes one after the other.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&SearchResult<G::Move, G::Score>) + Send + 'static,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// A flag that stops the search when it is set to true, from any thread, such as when the
+    /// user presses stop. The search then returns the best move it found so far: the best move
+    /// of the deepest completed depth, or of the moves searched so far when no depth was
+    /// completed. The flag is cleared again once the search has stopped, ready for the next
+    /// search; a flag set between two searches stops the next one right away.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    /// The transposition table shared by every search made with this Searcher.
+    pub fn table(&self) -> &TranspositionTable<G::Move, G::Score> {
+        &self.table
+    }
+
+    /// The number of positions visited by the last search.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Same as `minimax_search`, but remembers searched positions for later calls.
+    pub fn search(&mut self, game: &G, depth: usize) -> Option<G::Move> {
+        self.analyze(game, depth).best_move
+    }
+
+    /// Same as `minimax_search_timed`, but remembers searched positions for later calls.
+    pub fn search_timed(
+        &mut self,
+        game: &G,
+        max_depth: usize,
+        budget: Duration,
+    ) -> Option<G::Move> {
+        self.analyze_timed(game, max_depth, budget).best_move
+    }
+
+    /// Same as `minimax_analyze`, but remembers searched positions for later calls.
+    pub fn analyze(&mut self, game: &G, depth: usize) -> SearchResult<G::Move, G::Score> {
+        let root = self.policy_root(Self::root);
+        self.fixed_depth(game, depth, root)
+    }
+
+    /// Same as `minimax_analyze_multi_pv`, but remembers searched positions for later calls.
+    pub fn analyze_multi_pv(
+        &mut self,
+        game: &G,
+        depth: usize,
+        count: usize,
+    ) -> SearchResult<G::Move, G::Score> {
+        self.multi_pv = count;
+        self.fixed_depth(game, depth, Self::multi_pv_root)
+    }
+
+    /// Same as `analyze_multi_pv`, but deepens the search until `max_depth` or the time budget
+    /// runs out, like `minimax_analyze_timed`, and ranks the moves of the deepest completed search.
+    pub fn analyze_multi_pv_timed(
+        &mut self,
+        game: &G,
+        max_depth: usize,
+        budget: Duration,
+        count: usize,
+    ) -> SearchResult<G::Move, G::Score> {
+        self.multi_pv = count;
+        self.deepening(game, max_depth, Limit::Time(budget), Self::multi_pv_root)
+    }
+
+    /// Same as `minimax_search_nodes`, but remembers searched positions for later calls.
+    /// The positions it remembers change what later searches visit, so a Searcher only
+    /// repeats itself when it is given the same searches in the same order.
+    pub fn search_nodes(&mut self, game: &G, max_depth: usize, nodes: u64) -> Option<G::Move> {
+        self.analyze_nodes(game, max_depth, nodes).best_move
+    }
+
+    /// Same as `minimax_analyze_nodes`, but remembers searched positions for later calls.
+    pub fn analyze_nodes(
+        &mut self,
+        game: &G,
+        max_depth: usize,
+        nodes: u64,
+    ) -> SearchResult<G::Move, G::Score> {
+        let root = self.policy_root(Self::root);
+        self.deepening(game, max_depth, Limit::Nodes(nodes), root)
+    }
+
+    /// Same as `minimax_search_beam`, but makes use of the positions remembered by earlier
+    /// full-width searches. The score of a position searched with a beam only holds for the moves
+    /// in the beam, so beam searches leave the table as it was.
+    pub fn search_beam(&mut self, game: &G, depth: usize, width: usize) -> Option<G::Move> {
+        self.analyze_beam(game, depth, width).best_move
+    }
+
+    /// Same as `minimax_analyze_beam`, but makes use of the positions remembered by earlier
+    /// full-width searches.
+    pub fn analyze_beam(
+        &mut self,
+        game: &G,
+        depth: usize,
+        width: usize,
+    ) -> SearchResult<G::Move, G::Score> {
+        self.beam_width = Some(width.max(1));
+        let root = self.policy_root(Self::root);
+        let result = self.fixed_depth(game, depth, root);
+        self.beam_width = None;
+        result
+    }
+
+    /// Same as `minimax_search_mtdf`, but remembers searched positions for later calls.
+    pub fn search_mtdf(&mut self, game: &G, depth: usize) -> Option<G::Move> {
+        self.analyze_mtdf(game, depth).best_move
+    }
+
+    /// Same as `analyze`, but finds the score with MTD(f) (see `minimax_search_mtdf`).
+    pub fn analyze_mtdf(&mut self, game: &G, depth: usize) -> SearchResult<G::Move, G::Score> {
+        self.fixed_depth(game, depth, Self::mtdf)
+    }
+
+    /// Same as `analyze_timed`, but finds the score of every depth with MTD(f) (see
+    /// `minimax_search_mtdf`), starting from the score of the depth before.
+    pub fn analyze_mtdf_timed(
+        &mut self,
+        game: &G,
+        max_depth: usize,
+        budget: Duration,
+    ) -> SearchResult<G::Move, G::Score> {
+        self.deepening(game, max_depth, Limit::Time(budget), Self::mtdf)
+    }
+
+    /// Function: Alpha-beta search of a position to the given depth, for drivers of the search
+    /// that need to choose the window themselves, such as MTD(f).
+    ///
+    /// The score is from the point of view of the player to move, and fail-soft: a score at or
+    /// below `alpha` is an upper bound of the true score, a score at or above `beta` a lower
+    /// bound, and a score in between is exact. The transposition table is shared with every
+    /// other search of this Searcher, and the nodes visited add up until the next search. The
+    /// stop flag (see `stop_flag`) is not cleared here, and makes the score meaningless.
+    ///
+    /// # Arguments
+    /// * `depth` - A usize that holds the depth of the minimax tree
+    ///
+    /// * `alpha` - A score below which scores only need to be known as upper bounds
+    ///
+    /// * `beta` - A score above which scores only need to be known as lower bounds
+    ///
+    /// Returns the score of the position
+    pub fn alpha_beta(
+        &mut self,
+        game: &G,
+        depth: usize,
+        alpha: G::Score,
+        beta: G::Score,
+    ) -> G::Score {
+        self.deadline = None;
+        self.timed_out = false;
+        self.start_root(depth);
+        let mut game = game.clone();
+        self.minimax_helper(&mut game, depth, alpha, beta)
+    }
+
+    /// Same as `minimax_analyze_timed`, but remembers searched positions for later calls.
+    pub fn analyze_timed(
+        &mut self,
+        game: &G,
+        max_depth: usize,
+        budget: Duration,
+    ) -> SearchResult<G::Move, G::Score> {
+        let root = self.policy_root(Self::root);
+        self.deepening(game, max_depth, Limit::Time(budget), root)
+    }
+
+    fn fixed_depth(
+        &mut self,
+        game: &G,
+        depth: usize,
+        root: RootSearch<G>,
+    ) -> SearchResult<G::Move, G::Score> {
+        let started = Instant::now();
+        self.start(None);
+        if depth == 0 {
+            // Choose first available move.
+            return self.unsearched(game, started);
+        }
+        let (best_move, score) = root(self, game, depth, -G::Score::INFINITY, G::Score::INFINITY);
+        if best_move.is_none() && self.timed_out {
+            // Stopped before a single move was searched
+            return self.unsearched(game, started);
+        }
+        // A search stopped part way through the root moves has only completed the depth below
+        let completed = if self.timed_out { depth - 1 } else { depth };
+        if !self.timed_out {
+            self.report(&best_move, score, depth, started);
+        }
+        let result = self.result(best_move, score, completed, started);
+        self.apply_policy(game, result)
+    }
+
+    fn deepening(
+        &mut self,
+        game: &G,
+        max_depth: usize,
+        limit: Limit,
+        root: RootSearch<G>,
+    ) -> SearchResult<G::Move, G::Score> {
+        let started = Instant::now();
+        match limit {
+            Limit::Time(budget) => self.start(Some(started + budget)),
+            Limit::Nodes(nodes) => {
+                self.start(None);
+                self.node_limit = Some(nodes);
+            }
+        }
+        let mut completed = None;
+        let mut guess = None;
+        for depth in 1..=max_depth {
+            let (best_move, score) = self.aspiration(game, depth, guess, root);
+            if self.timed_out {
+                if completed.is_none() && best_move.is_some() {
+                    // Not even the first depth was completed, the moves searched so far will do
+                    completed = Some((
+                        best_move,
+                        score,
+                        depth - 1,
+                        self.pv[0].clone(),
+                        self.ranked.clone(),
+                    ));
+                }
+                break;
+            }
+            guess = Some(score);
+            self.report(&best_move, score, depth, started);
+            completed = Some((
+                best_move,
+                score,
+                depth,
+                self.pv[0].clone(),
+                self.ranked.clone(),
+            ));
+            if !self.hit_cutoff {
+                // Every line ended before reaching the depth limit, searching deeper changes nothing
+                break;
+            }
+        }
+        match completed {
+            Some((best_move, score, depth, pv, ranked)) => {
+                self.pv[0] = pv;
+                self.ranked = ranked;
+                let result = self.result(best_move, score, depth, started);
+                self.apply_policy(game, result)
+            }
+            None => self.unsearched(game, started),
+        }
+    }
+
+    // Searches the root within a window around `guess`, the score of the previous depth, when
+    // aspiration windows are turned on. The window is widened past the score on whichever side
+    // the score falls outside of it, until the score lands inside. Widening from the score
+    // rather than the guess keeps up with scores that a wider delta alone would never reach,
+    // such as the first number of a pair.
+    fn aspiration(
+        &mut self,
+        game: &G,
+        depth: usize,
+        guess: Option<G::Score>,
+        root: RootSearch<G>,
+    ) -> (Option<G::Move>, G::Score) {
+        let (Some(guess), Some(delta)) = (guess, self.config.aspiration_window) else {
+            return root(self, game, depth, -G::Score::INFINITY, G::Score::INFINITY);
+        };
+        // Once the window is wider than a won game, it might as well take in everything
+        let below = |score, delta| {
+            if delta > G::Score::WIN {
+                -G::Score::INFINITY
+            } else {
+                score - delta
+            }
+        };
+        let above = |score, delta| {
+            if delta > G::Score::WIN {
+                G::Score::INFINITY
+            } else {
+                score + delta
+            }
+        };
+        let mut delta = G::Score::steps(delta.max(1));
+        let mut alpha = below(guess, delta);
+        let mut beta = above(guess, delta);
+        loop {
+            let (best_move, score) = root(self, game, depth, alpha, beta);
+            if delta <= G::Score::WIN {
+                delta = delta + delta;
+            }
+            if score <= alpha && alpha > -G::Score::INFINITY && !self.timed_out {
+                alpha = below(score, delta);
+            } else if score >= beta && beta < G::Score::INFINITY && !self.timed_out {
+                beta = above(score, delta);
+            } else {
+                return (best_move, score);
+            }
+        }
+    }
+
+    // Tells the progress callback, if there is one, about a completed depth
+    fn report(
+        &mut self,
+        best_move: &Option<G::Move>,
+        score: G::Score,
+        depth: usize,
+        started: Instant,
+    ) {
+        if self.progress.is_none() {
+            return;
+        }
+        let result = SearchResult {
+            best_move: best_move.clone(),
+            score,
+            principal_variation: self.pv[0].clone(),
+            depth,
+            nodes: self.nodes,
+            cutoffs: self.cutoffs,
+            elapsed: started.elapsed(),
+            ranked_moves: self.ranked.clone(),
+        };
+        if let Some(progress) = self.progress.as_mut() {
+            progress(&result);
+        }
+    }
+
+    fn result(
+        &mut self,
+        best_move: Option<G::Move>,
+        score: G::Score,
+        depth: usize,
+        started: Instant,
+    ) -> SearchResult<G::Move, G::Score> {
+        // Whatever stopped the search, the next one starts afresh
+        self.stop.store(false, Ordering::Relaxed);
+        SearchResult {
+            best_move,
+            score,
+            principal_variation: std::mem::take(&mut self.pv[0]),
+            depth,
+            nodes: self.nodes,
+            cutoffs: self.cutoffs,
+            elapsed: started.elapsed(),
+            ranked_moves: std::mem::take(&mut self.ranked),
+        }
+    }
+
+    // The result when there was no search to speak of, either because the depth was 0 or
+    // because the time ran out before the first search completed
+    fn unsearched(&mut self, game: &G, started: Instant) -> SearchResult<G::Move, G::Score> {
+        let best_move = game.possible_moves().next();
+        self.stop.store(false, Ordering::Relaxed);
+        SearchResult {
+            principal_variation: best_move.iter().cloned().collect(),
+            best_move,
+            score: game.to_move().perspective(evaluate(game, 0)),
+            depth: 0,
+            nodes: self.nodes,
+            cutoffs: self.cutoffs,
+            elapsed: started.elapsed(),
+            ranked_moves: Vec::new(),
+        }
+    }
+
+    fn start(&mut self, deadline: Option<Instant>) {
+        for helper in self.helpers.iter_mut() {
+            helper.start(deadline);
+        }
+        self.deadline = deadline;
+        self.node_limit = None;
+        self.ranked.clear();
+        self.nodes = 0;
+        self.cutoffs = 0;
+        self.timed_out = false;
+        // Killer moves belong to the last position searched, while the history of a move
+        // stays useful for a while, but should fade as the game moves on
+        self.killers.clear();
+        for score in self.history.values_mut() {
+            *score /= 2;
+        }
+    }
+
+    fn start_root(&mut self, depth: usize) {
+        self.ply = 0;
+        self.ranked.clear();
+        self.hit_cutoff = false;
+        self.pv.resize(depth + 1, Vec::new());
+        self.pv[0].clear();
+    }
+
+    // Returns the move that corresponds with best heuristic, together with that heuristic.
+    // Outside of the window between alpha and beta the heuristic is only a bound.
+    fn root(
+        &mut self,
+        game: &G,
+        depth: usize,
+        mut alpha: G::Score,
+        beta: G::Score,
+    ) -> (Option<G::Move>, G::Score) {
+        self.start_root(depth);
+        let mut game = game.clone();
+        let game = &mut game;
+        let player = game.to_move();
+        let key = Self::key(game);
+        let window_alpha = alpha;
+        let ties = self.config.policy == RootPolicy::RandomBest;
+        let mut best_move = (None, -G::Score::INFINITY); // We're going to maximize heuristic
+        for (i, mymove) in self.ordered_moves(game, key, 0).into_iter().enumerate() {
+            // A move that ties with the best fails low, unless the window starts just below
+            let low = if ties { below_by_step(alpha) } else { alpha };
+            let h = self.search_pvs(game, &mymove, i == 0, depth - 1, low, beta);
+            if self.timed_out {
+                // The moves searched before are all the search has to go on
+                break;
+            }
+            let exact = h > low && h < beta;
             if h > best_move.1 {
+                self.extend_pv(0, &mymove);
+                self.ranked.clear();
+                if ties && exact {
+                    self.rank(mymove.clone(), h, self.pv[0].clone());
+                }
                 best_move = (Some(mymove), h);
+            } else if ties && exact && h == best_move.1 {
+                let mut line = vec![mymove.clone()];
+                line.extend_from_slice(&self.pv[1]);
+                self.rank(mymove, h, line);
             }
             if h > alpha {
                 alpha = h;
             }
+            if beta <= alpha {
+                break;
+            }
         }
-    } else {
-        // Choose first available move.
-        let mut moves = game.possible_moves();
-        return moves.next();
+        if best_move.0.is_none() && !self.timed_out {
+            // No possible moves, the position speaks for itself
+            best_move.1 = player.perspective(evaluate(game, 0));
+        }
+        if let (Some(key), false) = (key, self.timed_out) {
+            let (best, h) = best_move.clone();
+            self.remember(key, depth, h, bound(h, window_alpha, beta), best);
+        }
+        best_move
     }
-    best_move.0 // Return the move that corresponds with best heuristic
-}
 
-// Need a helper because the client shouldn't provide alpha and beta
-// also nice because we don't have to have return valus of structs/tuples, can just do an isize
-// that corresponds to the best value for the immediately next move
-fn minimax_helper<G>(
-    game: &G,
-    depth: usize,
-    is_opponent: bool,
-    mut alpha: isize,
-    mut beta: isize,
-) -> isize
-where
-    G: HeuristicGameTree,
-{
-    //let mut current_heuristic = game.heuristic();
-    if depth > 0 {
-        let mut heuristic = if is_opponent { MIN+1 } else { MAX-1 };
-        for mymove in game.possible_moves() {
+    // Same as root, but ranks the best multi_pv moves: every move is searched with alpha at the
+    // score of the last move on the list so far, so the moves that make the list get an exact
+    // score. The list leaves no room for a narrower window, so the window is always the whole.
+    fn multi_pv_root(
+        &mut self,
+        game: &G,
+        depth: usize,
+        _alpha: G::Score,
+        _beta: G::Score,
+    ) -> (Option<G::Move>, G::Score) {
+        self.start_root(depth);
+        let mut game = game.clone();
+        let game = &mut game;
+        let player = game.to_move();
+        let key = Self::key(game);
+        let count = self.multi_pv.max(1);
+        for mymove in self.ordered_moves(game, key, 0) {
+            let full = self.ranked.len() < count;
+            let alpha = if full {
+                -G::Score::INFINITY
+            } else {
+                self.ranked[count - 1].score
+            };
+            let h = self.search_pvs(game, &mymove, full, depth - 1, alpha, G::Score::INFINITY);
+            if self.timed_out {
+                break;
+            }
+            if h > alpha {
+                self.extend_pv(0, &mymove);
+                self.rank(mymove, h, self.pv[0].clone());
+                self.ranked.truncate(count);
+            }
+        }
+        let Some(best) = self.ranked.first() else {
+            // No possible moves, the position speaks for itself
+            self.pv[0].clear();
+            let score = if self.timed_out {
+                G::Score::ZERO
+            } else {
+                player.perspective(evaluate(game, 0))
+            };
+            return (None, score);
+        };
+        let (best_move, score) = (best.next_move.clone(), best.score);
+        self.pv[0] = best.principal_variation.clone();
+        if let (Some(key), false) = (key, self.timed_out) {
+            self.remember(key, depth, score, Bound::Exact, Some(best_move.clone()));
+        }
+        (Some(best_move), score)
+    }
+
+    // Adds a move with an exact score to the ranked moves, behind the moves that score as well
+    fn rank(&mut self, next_move: G::Move, score: G::Score, principal_variation: Vec<G::Move>) {
+        let at = self.ranked.partition_point(|line| line.score >= score);
+        self.ranked.insert(
+            at,
+            RankedMove {
+                next_move,
+                score,
+                principal_variation,
+            },
+        );
+    }
+
+    // The root search the policy needs: softmax needs the exact score of every move
+    fn policy_root(&mut self, root: RootSearch<G>) -> RootSearch<G> {
+        match self.config.policy {
+            RootPolicy::Softmax { .. } => {
+                self.multi_pv = usize::MAX;
+                Self::multi_pv_root
+            }
+            _ => root,
+        }
+    }
+
+    // Swaps the best move of a search for the move the policy chooses, with its line of play
+    fn apply_policy(
+        &mut self,
+        game: &G,
+        mut result: SearchResult<G::Move, G::Score>,
+    ) -> SearchResult<G::Move, G::Score> {
+        // Only the ranked moves have exact scores to weigh against each other
+        let known = &result.ranked_moves;
+        let chosen = match self.config.policy {
+            RootPolicy::Best => None,
+            RootPolicy::RandomBest => {
+                let ties: Vec<_> = known
+                    .iter()
+                    .filter(|line| line.score == result.score)
+                    .collect();
+                (!ties.is_empty())
+                    .then(|| ties[self.rng.below(ties.len())])
+                    .map(|line| (line.next_move.clone(), line.principal_variation.clone()))
+            }
+            RootPolicy::Softmax { temperature } => {
+                let weights: Vec<f64> = known
+                    .iter()
+                    .map(|line| {
+                        if line.score == result.score {
+                            1.0
+                        } else if temperature > 0.0 {
+                            ((line.score - result.score).to_f64() / temperature).exp()
+                        } else {
+                            // A temperature of 0 leaves only the best moves
+                            0.0
+                        }
+                    })
+                    .collect();
+                let mut left = self.rng.next_f64() * weights.iter().sum::<f64>();
+                let i = weights.iter().position(|weight| {
+                    left -= weight;
+                    left < 0.0
+                });
+                i.map(|i| {
+                    (
+                        known[i].next_move.clone(),
+                        known[i].principal_variation.clone(),
+                    )
+                })
+            }
+            RootPolicy::EpsilonGreedy { epsilon } => {
+                let moves: Vec<G::Move> = game.possible_moves().collect();
+                if self.rng.next_f64() < epsilon && !moves.is_empty() {
+                    let chosen = moves[self.rng.below(moves.len())].clone();
+                    let line = known.iter().find(|line| line.next_move == chosen);
+                    let pv = line.map_or_else(
+                        || vec![chosen.clone()],
+                        |line| line.principal_variation.clone(),
+                    );
+                    Some((chosen, pv))
+                } else {
+                    None
+                }
+            }
+        };
+        if let Some((chosen, pv)) = chosen {
+            result.best_move = Some(chosen);
+            result.principal_variation = pv;
+        }
+        result
+    }
+
+    // Same as root, but narrows down the score between alpha and beta with null window searches
+    // of the root, starting from the score the table remembers for the position
+    fn mtdf(
+        &mut self,
+        game: &G,
+        depth: usize,
+        alpha: G::Score,
+        beta: G::Score,
+    ) -> (Option<G::Move>, G::Score) {
+        let guess = Self::key(game)
+            .and_then(|key| self.table.probe(key))
+            .map_or(G::Score::ZERO, |entry| entry.score);
+        let (mut lower, mut upper) = (alpha, beta);
+        let mut score = guess.clamp(lower, upper);
+        let mut best = (None, Vec::new());
+        let mut hit_cutoff = false;
+        while lower < upper {
+            // The window sits right above the score when it can, so that the score of a search
+            // that fails high is the new lower bound
+            let window = score.max(lower + G::Score::STEP);
+            let (best_move, h) = self.root(game, depth, window - G::Score::STEP, window);
+            if self.timed_out {
+                return (None, G::Score::ZERO);
+            }
+            hit_cutoff |= self.hit_cutoff;
+            score = h;
+            if h < window {
+                upper = h;
+                if best.0.is_none() {
+                    best = (best_move, self.pv[0].clone());
+                }
+            } else {
+                // Only a search that fails high knows its best move is as good as the score
+                lower = h;
+                best = (best_move, self.pv[0].clone());
+            }
+        }
+        self.pv[0] = best.1;
+        self.hit_cutoff = hit_cutoff;
+        (best.0, score)
+    }
+
+    // Searches a move, with Principal Variation Search when it is turned on: every move but the
+    // first is only searched with a null window, which is enough to prove that it is no better
+    // than the best move so far. Only a move that turns out to be better is searched again with
+    // the whole window.
+    fn search_pvs(
+        &mut self,
+        game: &mut G,
+        mymove: &G::Move,
+        first: bool,
+        depth: usize,
+        alpha: G::Score,
+        beta: G::Score,
+    ) -> G::Score {
+        if !self.config.pvs || first || alpha + G::Score::STEP >= beta {
+            return self.search_move(game, mymove, depth, alpha, beta);
+        }
+        let h = self.search_move(game, mymove, depth, alpha, alpha + G::Score::STEP);
+        if h > alpha && h < beta && !self.timed_out {
+            self.search_move(game, mymove, depth, alpha, beta)
+        } else {
+            h
+        }
+    }
+
+    // Plays a move and returns the heuristic of the position it leads to, seen from the side of
+    // the player who made it. The game is left as it was found, either because the move was
+    // played on a clone of it, or because the move was undone afterwards.
+    fn search_move(
+        &mut self,
+        game: &mut G,
+        mymove: &G::Move,
+        depth: usize,
+        alpha: G::Score,
+        beta: G::Score,
+    ) -> G::Score {
+        let player = game.to_move();
+        self.ply += 1;
+        let h = if self.config.undo_moves && game.can_undo() {
+            game.execute_move(mymove);
+            let h = self.child_heuristic(player, game, depth, alpha, beta);
+            game.undo_move(mymove);
+            h
+        } else {
             let mut next_state = game.clone();
-            let opp = next_state.execute_move(&mymove.clone(), is_opponent);
-            let h = minimax_helper(&next_state, depth - 1, opp, alpha, beta);
-            if (h > heuristic && is_opponent) || (h < heuristic && !is_opponent) {
-                heuristic = h;
+            next_state.execute_move(mymove); // Need to clone, standard procedure with minimax
+            self.child_heuristic(player, &mut next_state, depth, alpha, beta)
+        };
+        self.ply -= 1;
+        h
+    }
+
+    // The heuristic of a position reached by a move of `player`, seen from that player's side.
+    // Usually the other player moves next, so their window and score are flipped around, but
+    // if the move earned `player` another turn they are passed along unchanged.
+    fn child_heuristic(
+        &mut self,
+        player: Player,
+        child: &mut G,
+        depth: usize,
+        alpha: G::Score,
+        beta: G::Score,
+    ) -> G::Score {
+        if child.to_move() == player {
+            self.minimax_helper(child, depth, alpha, beta)
+        } else {
+            -self.minimax_helper(child, depth, -beta, -alpha)
+        }
+    }
+
+    // The alpha-beta search behind every driver, root or alpha_beta. Nice because we don't have
+    // to have return valus of structs/tuples, can just do a score that corresponds to the best
+    // value for the immediately next move
+    fn minimax_helper(
+        &mut self,
+        game: &mut G,
+        depth: usize,
+        mut alpha: G::Score,
+        mut beta: G::Score,
+    ) -> G::Score {
+        if self.out_of_time() {
+            // The result is thrown away by the caller, any value will do
+            return G::Score::ZERO;
+        }
+        let ply = self.ply;
+        if self.pv.len() < ply + 2 {
+            // Quiescence search can go past the depth the lines were made room for
+            self.pv.resize(ply + 2, Vec::new());
+        }
+        self.pv[ply].clear();
+        let player = game.to_move();
+        if game.outcome() != Outcome::Ongoing {
+            return player.perspective(evaluate(game, ply));
+        }
+        let key = Self::key(game);
+        let solved = key
+            .zip(self.tablebase.as_ref())
+            .and_then(|(key, tablebase)| tablebase.probe_key(key));
+        if let Some(value) = solved {
+            // The tablebase knows the outcome of the game from here, no search needed
+            return value.score(ply);
+        }
+        if depth == 0 {
+            // End of depth, return
+            self.hit_cutoff = true;
+            return self.quiesce(game, alpha, beta);
+        }
+        let remembered = key
+            .and_then(|key| self.table.probe(key))
+            .map(|entry| (entry.depth, from_table(entry.score, ply), entry.bound));
+        if let Some((entry_depth, score, bound)) = remembered {
+            if entry_depth >= depth {
+                // The entry may hide lines that were cut off, so deepening could still help
+                self.hit_cutoff = true;
+                match bound {
+                    Bound::Exact => return score,
+                    Bound::Lower => alpha = alpha.max(score),
+                    Bound::Upper => beta = beta.min(score),
+                }
+                if beta <= alpha {
+                    return score;
+                }
+            }
+        }
+        if let Some(score) = self.null_move(game, depth, beta) {
+            self.cutoffs += 1;
+            return score;
+        }
+        // Quiet moves one ply before the depth limit can be skipped if even this much is not
+        // enough to reach alpha
+        let futile = match self.config.futility_margin {
+            Some(margin) if depth == 1 && !decided(alpha) => {
+                let margin = G::Score::steps(margin);
+                Some(player.perspective(game.heuristic()) + margin).filter(|&score| score <= alpha)
             }
-            if is_opponent && h > alpha {
+            _ => None,
+        };
+        let (window_alpha, window_beta) = (alpha, beta);
+        let mut heuristic = -G::Score::INFINITY;
+        let mut best_move = None;
+        for (i, mymove) in self.ordered_moves(game, key, ply).into_iter().enumerate() {
+            let quiet = !game.is_noisy(&mymove);
+            if let (Some(score), Some(_), true) = (futile, &best_move, quiet) {
+                heuristic = heuristic.max(score);
+                continue;
+            }
+            let reduce = self.config.late_move_reductions
+                && i >= FULL_DEPTH_MOVES
+                && depth >= REDUCTION_MIN_DEPTH
+                && quiet;
+            let h = if reduce {
+                let h = self.search_move(game, &mymove, depth - 2, alpha, alpha + G::Score::STEP);
+                if h > alpha && !self.timed_out {
+                    self.search_pvs(game, &mymove, false, depth - 1, alpha, beta)
+                } else {
+                    h
+                }
+            } else {
+                self.search_pvs(game, &mymove, i == 0, depth - 1, alpha, beta)
+            };
+            if h > alpha {
                 alpha = h;
-            } else if !is_opponent && h < beta {
-                beta = h;
             }
-            if beta <= alpha {
+            if beta <= alpha && !self.timed_out {
+                self.cutoffs += 1;
+                self.remember_cutoff(&mymove, depth, ply);
+            }
+            if h > heuristic {
+                heuristic = h;
+                self.extend_pv(ply, &mymove);
+                best_move = Some(mymove);
+            }
+            if beta <= alpha || self.timed_out {
+                break;
+            }
+        }
+        if best_move.is_none() {
+            // No possible moves, but the game goes on, so the position speaks for itself
+            heuristic = player.perspective(game.heuristic());
+        }
+        if let (Some(key), false) = (key, self.timed_out) {
+            let bound = bound(heuristic, window_alpha, window_beta);
+            self.remember(key, depth, to_table(heuristic, ply), bound, best_move);
+        }
+        heuristic
+    }
+
+    // Null-move pruning: the score that proves the position is at least beta if the player to
+    // move can pass and still get beta, searched a few plies shallower. Passing twice in a row
+    // proves nothing, and neither does passing when the score is about a won or lost game.
+    fn null_move(&mut self, game: &mut G, depth: usize, beta: G::Score) -> Option<G::Score> {
+        if !self.config.null_move
+            || depth <= NULL_MOVE_REDUCTION
+            || self.pass_ply == Some(self.ply)
+            || decided(beta)
+            || game.to_move().perspective(game.heuristic()) < beta
+        {
+            return None;
+        }
+        let pass = game.pass_move()?;
+        let before = self.pass_ply.replace(self.ply + 1);
+        let h = self.search_move(
+            game,
+            &pass,
+            depth - 1 - NULL_MOVE_REDUCTION,
+            beta - G::Score::STEP,
+            beta,
+        );
+        self.pass_ply = before;
+        if h < beta || self.timed_out {
+            return None;
+        }
+        // A win found after passing is not a win the position can be sure of
+        Some(if decided(h) { beta } else { h })
+    }
+
+    // The heuristic of a position at the end of the depth. If the game marks some of its moves as
+    // noisy, they are searched on until the position is quiet, so that the heuristic is not taken
+    // in the middle of an exchange. A player who has quiet moves as well can stop there (stand
+    // pat), so the heuristic of the position is the least they can get.
+    fn quiesce(&mut self, game: &mut G, mut alpha: G::Score, beta: G::Score) -> G::Score {
+        let player = game.to_move();
+        let stand_pat = player.perspective(game.heuristic());
+        if !self.config.quiescence {
+            return stand_pat;
+        }
+        let moves: Vec<G::Move> = game.possible_moves().collect();
+        let noisy: Vec<G::Move> = moves.iter().filter(|m| game.is_noisy(m)).cloned().collect();
+        if noisy.is_empty() {
+            return stand_pat;
+        }
+        let mut heuristic = -G::Score::INFINITY;
+        if noisy.len() < moves.len() {
+            heuristic = stand_pat;
+            if heuristic >= beta {
+                return heuristic;
+            }
+            alpha = alpha.max(heuristic);
+        }
+        for mymove in noisy {
+            let h = self.search_move(game, &mymove, 0, alpha, beta);
+            if h > heuristic {
+                heuristic = h;
+                self.extend_pv(self.ply, &mymove);
+            }
+            alpha = alpha.max(h);
+            if beta <= alpha && !self.timed_out {
+                self.cutoffs += 1;
+            }
+            if beta <= alpha || self.timed_out {
                 break;
             }
         }
-        // If no moves, and is opponent, this means the player won, so return MIN
-        // else if no moves and is not opponent, this means AI won, return MAX
         heuristic
     }
-    // End of depth, return
-    else {
-        game.heuristic()
+
+    // Moves of the position, with the best move from an earlier search of it tried first
+    fn ordered_moves(&self, game: &G, key: Option<u64>, ply: usize) -> Vec<G::Move> {
+        let mut moves: Vec<G::Move> = game.possible_moves().collect();
+        if let Some(width) = self.beam_width {
+            Self::beam(game, &mut moves, width, ply);
+        }
+        if self.config.move_ordering {
+            let killers = self.killers.get(ply);
+            // The sort is stable, so moves that look alike keep the order the game gave them
+            moves.sort_by_cached_key(|m| {
+                let is_killer = killers.is_some_and(|k| k.iter().any(|k| k.as_ref() == Some(m)));
+                let history = self.history.get(m).copied().unwrap_or(0);
+                Reverse((is_killer, history, game.move_priority(m)))
+            });
+        }
+        let remembered = key
+            .and_then(|key| self.table.probe(key))
+            .and_then(|entry| entry.best_move.as_ref());
+        if let Some(best) = remembered {
+            if let Some(i) = moves.iter().position(|m| m == best) {
+                moves[..=i].rotate_right(1);
+            }
+        }
+        moves
+    }
+
+    // Keeps only the `width` moves that lead to the best heuristic for the player making them,
+    // for beam search. Move ordering sorts the ones kept afterwards.
+    fn beam(game: &G, moves: &mut Vec<G::Move>, width: usize, ply: usize) {
+        if moves.len() <= width {
+            return;
+        }
+        let player = game.to_move();
+        moves.sort_by_cached_key(|mymove| {
+            let mut next_state = game.clone();
+            next_state.execute_move(mymove);
+            Reverse(player.perspective(evaluate(&next_state, ply + 1)))
+        });
+        moves.truncate(width);
+    }
+
+    // The best line from this ply is now the move, followed by the best line of its child
+    fn extend_pv(&mut self, ply: usize, mymove: &G::Move) {
+        let (line, rest) = self.pv[ply..].split_at_mut(1);
+        line[0].clear();
+        line[0].push(mymove.clone());
+        line[0].extend_from_slice(&rest[0]);
+    }
+
+    // A move just refuted the line leading here, so try it early in similar positions
+    fn remember_cutoff(&mut self, mymove: &G::Move, depth: usize, ply: usize) {
+        if ply >= self.killers.len() {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0].as_ref() != Some(mymove) {
+            killers[1] = killers[0].take();
+            killers[0] = Some(mymove.clone());
+        }
+        *self.history.entry(mymove.clone()).or_insert(0) += (depth * depth) as u64;
+    }
+
+    fn key(game: &G) -> Option<u64> {
+        side_key(game)
+    }
+
+    // Remembers a searched position, unless the search only looked at a beam of the moves, whose
+    // score a full-width search must not trust
+    fn remember(
+        &mut self,
+        key: u64,
+        depth: usize,
+        score: G::Score,
+        bound: Bound,
+        best_move: Option<G::Move>,
+    ) {
+        if self.beam_width.is_none() {
+            self.table.store(key, depth, score, bound, best_move);
+        }
+    }
+
+    // Whether the search has to stop: out of time or nodes, or told to stop
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+        if self.node_limit.is_some_and(|limit| self.nodes > limit) {
+            self.timed_out = true;
+        }
+        if self.nodes.is_multiple_of(CLOCK_CHECK_INTERVAL) {
+            if let Some(deadline) = self.deadline {
+                self.timed_out |= Instant::now() >= deadline;
+            }
+            // Being stopped is much like running out of time
+            self.timed_out |= self.stop.load(Ordering::Relaxed);
+        }
+        self.timed_out
+    }
+}
+
+// A search of the moves of the root position to a given depth, within a window
+type RootSearch<G> = fn(
+    &mut Searcher<G>,
+    &G,
+    usize,
+    ScoreOf<G>,
+    ScoreOf<G>,
+) -> (Option<<G as HeuristicGameTree>::Move>, ScoreOf<G>);
+
+// The score type of a game
+type ScoreOf<G> = <G as HeuristicGameTree>::Score;
+
+// One root move searched by a helper thread: its place in the move order, its heuristic, whether
+// that heuristic is exact rather than an upper bound, and the line of play it starts
+type RootReport<M, S> = (usize, S, bool, Vec<M>);
+
+impl<G> Searcher<G>
+where
+    G: HeuristicGameTree + Send + Sync,
+    G::Move: Send + Sync,
+{
+    /// Same as `search`, but searches on `SearchConfig::threads` threads.
+    pub fn search_parallel(&mut self, game: &G, depth: usize) -> Option<G::Move> {
+        self.analyze_parallel(game, depth).best_move
+    }
+
+    /// Same as `search_timed`, but searches on `SearchConfig::threads` threads.
+    pub fn search_parallel_timed(
+        &mut self,
+        game: &G,
+        max_depth: usize,
+        budget: Duration,
+    ) -> Option<G::Move> {
+        self.analyze_parallel_timed(game, max_depth, budget)
+            .best_move
+    }
+
+    /// Same as `analyze`, but searches on `SearchConfig::threads` threads.
+    ///
+    /// The moves of the position are shared out between the threads, and every thread
+    /// searches the moves it is given to the full depth. The threads share the best heuristic
+    /// found so far, so a thread can still skip the parts of its moves that cannot beat it.
+    /// Every thread has a transposition table of its own, which like the table of the Searcher
+    /// is kept for later calls.
+    pub fn analyze_parallel(&mut self, game: &G, depth: usize) -> SearchResult<G::Move, G::Score> {
+        let root = self.policy_root(Self::parallel_root);
+        self.fixed_depth(game, depth, root)
+    }
+
+    /// Same as `analyze_timed`, but searches on `SearchConfig::threads` threads.
+    pub fn analyze_parallel_timed(
+        &mut self,
+        game: &G,
+        max_depth: usize,
+        budget: Duration,
+    ) -> SearchResult<G::Move, G::Score> {
+        let root = self.policy_root(Self::parallel_root);
+        self.deepening(game, max_depth, Limit::Time(budget), root)
+    }
+
+    // Same as root, but with the moves searched by the helper Searchers on their own threads
+    fn parallel_root(
+        &mut self,
+        game: &G,
+        depth: usize,
+        alpha: G::Score,
+        beta: G::Score,
+    ) -> (Option<G::Move>, G::Score) {
+        self.start_root(depth);
+        let threads = self.config.threads.max(1);
+        if self.helpers.len() != threads {
+            let size = self.table.capacity();
+            self.helpers = (0..threads)
+                .map(|_| Searcher::with_table_size(size))
+                .collect();
+            for helper in self.helpers.iter_mut() {
+                helper.start(self.deadline);
+            }
+        }
+        for helper in self.helpers.iter_mut() {
+            helper.config = self.config.clone();
+            helper.tablebase = self.tablebase.clone();
+            helper.stop = Arc::clone(&self.stop);
+            helper.nodes = 0;
+            helper.cutoffs = 0;
+            helper.start_root(depth);
+        }
+        let player = game.to_move();
+        let key = Self::key(game);
+        let moves = self.ordered_moves(game, key, 0);
+        let next = AtomicUsize::new(0);
+        let start_alpha = alpha;
+        let alpha = Mutex::new(alpha);
+        let ties = self.config.policy == RootPolicy::RandomBest;
+        let reports: Vec<RootReport<G::Move, G::Score>> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .helpers
+                .iter_mut()
+                .map(|helper| {
+                    let (moves, next, alpha) = (&moves, &next, &alpha);
+                    scope.spawn(move || {
+                        let mut game = game.clone();
+                        let mut reports = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= moves.len() || helper.timed_out {
+                                return reports;
+                            }
+                            let mut window_alpha = *alpha.lock().unwrap();
+                            if ties {
+                                // Same as root, a move that ties with the best needs an exact score
+                                window_alpha = below_by_step(window_alpha);
+                            }
+                            let h = helper.search_move(
+                                &mut game,
+                                &moves[i],
+                                depth - 1,
+                                window_alpha,
+                                beta,
+                            );
+                            if helper.timed_out {
+                                // Cut short, so the heuristic says nothing about the move
+                                return reports;
+                            }
+                            // A heuristic at or below alpha is only a bound, and cannot be the best
+                            let exact = h > window_alpha;
+                            let mut shared = alpha.lock().unwrap();
+                            *shared = (*shared).max(h);
+                            drop(shared);
+                            helper.extend_pv(0, &moves[i]);
+                            reports.push((i, h, exact, helper.pv[0].clone()));
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        for helper in self.helpers.iter() {
+            self.nodes += helper.nodes;
+            self.cutoffs += helper.cutoffs;
+            self.timed_out |= helper.timed_out;
+            self.hit_cutoff |= helper.hit_cutoff;
+        }
+        // Same as the single threaded search, the first move in move order wins a tie. If every
+        // move failed low, they are all bounds, and the highest bound will do. After a stop, the
+        // moves searched before it are all the search has to go on.
+        let all_low = reports.iter().all(|report| !report.2);
+        let mut reports: Vec<RootReport<G::Move, G::Score>> = reports
+            .into_iter()
+            .filter(|report| report.2 || all_low)
+            .collect();
+        reports.sort_by_key(|report| (Reverse(report.1), report.0));
+        if ties {
+            for (i, h, exact, pv) in reports.iter() {
+                if *exact && *h == reports[0].1 && *h < beta {
+                    self.rank(moves[*i].clone(), *h, pv.clone());
+                }
+            }
+        }
+        let best_move = match reports.into_iter().next() {
+            Some((i, h, _, pv)) => {
+                self.pv[0] = pv;
+                (Some(moves[i].clone()), h)
+            }
+            None if self.timed_out => (None, -G::Score::INFINITY),
+            // No possible moves, the position speaks for itself
+            None => (None, player.perspective(evaluate(game, 0))),
+        };
+        if let (Some(key), false) = (key, self.timed_out) {
+            let (best, h) = best_move.clone();
+            self.remember(key, depth, h, bound(h, start_alpha, beta), best);
+        }
+        best_move
+    }
+}
+
+// The position key of a game, with the player to move mixed in
+pub(crate) fn side_key<G: HeuristicGameTree>(game: &G) -> Option<u64> {
+    let player = game.to_move();
+    game.position_key().map(|key| match player {
+        Player::Max => key,
+        Player::Min => key ^ MIN_TO_MOVE_KEY,
+    })
+}
+
+// What a fail-soft heuristic says about the true value, given the window it was searched with
+fn bound<S: Score>(heuristic: S, alpha: S, beta: S) -> Bound {
+    if heuristic <= alpha {
+        Bound::Upper
+    } else if heuristic >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    }
+}
+
+// The heuristic of a position, from Max's point of view, where a finished game counts as a
+// win or loss `ply` moves away from the root
+pub(crate) fn evaluate<G: HeuristicGameTree>(game: &G, ply: usize) -> G::Score {
+    match game.outcome() {
+        Outcome::Win => G::Score::WIN - G::Score::steps(ply),
+        Outcome::Loss => -(G::Score::WIN - G::Score::steps(ply)),
+        Outcome::Draw => G::Score::ZERO,
+        Outcome::Ongoing => game.heuristic(),
+    }
+}
+
+// Won and lost games are scored by their distance from the root, but the table can be probed
+// from a different root, so they are stored by their distance from the position itself
+fn to_table<S: Score>(score: S, ply: usize) -> S {
+    if !decided(score) {
+        score
+    } else if score > S::ZERO {
+        score + S::steps(ply)
+    } else {
+        score - S::steps(ply)
+    }
+}
+
+fn from_table<S: Score>(score: S, ply: usize) -> S {
+    if !decided(score) {
+        score
+    } else if score > S::ZERO {
+        score - S::steps(ply)
+    } else {
+        score + S::steps(ply)
+    }
+}
+
+// Whether a score is about a won or lost game rather than a heuristic
+fn decided<S: Score>(score: S) -> bool {
+    let won = S::WIN - S::steps(MAX_WIN_PLIES);
+    score > won || score < -won
+}
+
+// The score right below, so that a window starting there takes in the score itself
+fn below_by_step<S: Score>(score: S) -> S {
+    if score > -S::INFINITY {
+        score - S::STEP
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod minimax_tests {
+    use super::{
+        minimax_analyze, minimax_analyze_beam, minimax_analyze_multi_pv, minimax_analyze_nodes,
+        minimax_analyze_timed, minimax_search, minimax_search_beam, minimax_search_mtdf,
+        minimax_search_nodes, minimax_search_parallel, minimax_search_timed,
+    };
+    use super::{RootPolicy, SearchConfig, Searcher, MIN_TO_MOVE_KEY, WIN_SCORE};
+    use crate::rng::Rng;
+    use crate::test_games::{nim, Nim};
+    use crate::{HeuristicGameTree, Outcome, Player, Score};
+    use std::collections::HashSet;
+    use std::ops::{Add, Neg, Sub};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    // Both players move forward 1 to 5 steps for a number of turns, and whoever is ahead at the
+    // end wins. Moving further is always better, so passing never helps, and the larger moves
+    // come first in move order.
+    #[derive(Clone)]
+    struct Race {
+        lead: isize,
+        turns: usize,
+        to_move: Player,
+    }
+
+    fn race(turns: usize) -> Race {
+        Race {
+            lead: 0,
+            turns,
+            to_move: Player::Max,
+        }
+    }
+
+    impl HeuristicGameTree for Race {
+        type Move = isize;
+        type Score = isize;
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            Box::new((1..=5).filter(move |_| self.turns > 0))
+        }
+        fn heuristic(&self) -> isize {
+            self.lead
+        }
+        fn execute_move(&mut self, next_move: &Self::Move) {
+            self.lead += self.to_move.sign() * next_move;
+            if *next_move != 0 {
+                self.turns -= 1;
+            }
+            self.to_move = self.to_move.opponent();
+        }
+        fn to_move(&self) -> Player {
+            self.to_move
+        }
+        fn can_undo(&self) -> bool {
+            true
+        }
+        fn undo_move(&mut self, last_move: &Self::Move) {
+            self.to_move = self.to_move.opponent();
+            if *last_move != 0 {
+                self.turns += 1;
+            }
+            self.lead -= self.to_move.sign() * last_move;
+        }
+        fn position_key(&self) -> Option<u64> {
+            Some(((self.lead as u64) << 8) ^ self.turns as u64)
+        }
+        fn move_priority(&self, next_move: &Self::Move) -> isize {
+            *next_move
+        }
+        fn pass_move(&self) -> Option<Self::Move> {
+            Some(0)
+        }
+    }
+
+    // A score compared by its first number, and by the second when the first ties
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct Pair(i32, i32);
+
+    impl Neg for Pair {
+        type Output = Pair;
+        fn neg(self) -> Pair {
+            Pair(-self.0, -self.1)
+        }
+    }
+
+    impl Add for Pair {
+        type Output = Pair;
+        fn add(self, other: Pair) -> Pair {
+            Pair(self.0 + other.0, self.1 + other.1)
+        }
+    }
+
+    impl Sub for Pair {
+        type Output = Pair;
+        fn sub(self, other: Pair) -> Pair {
+            Pair(self.0 - other.0, self.1 - other.1)
+        }
+    }
+
+    impl Score for Pair {
+        const ZERO: Pair = Pair(0, 0);
+        const STEP: Pair = Pair(0, 1);
+        const WIN: Pair = Pair(i32::MAX / 2, 0);
+        const INFINITY: Pair = Pair(i32::MAX, 0);
+        fn steps(count: usize) -> Pair {
+            Pair(0, count as i32)
+        }
+        fn to_f64(self) -> f64 {
+            self.0 as f64
+        }
+    }
+
+    // Same as Race, but every move also earns style points, which only break ties in distance.
+    // Whoever is at least 2 steps ahead once the turns run out wins.
+    #[derive(Clone)]
+    struct StyleRace {
+        lead: Pair,
+        turns: usize,
+        to_move: Player,
+    }
+
+    impl HeuristicGameTree for StyleRace {
+        type Move = Pair;
+        type Score = Pair;
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            Box::new(
+                vec![Pair(1, 9), Pair(2, 0), Pair(2, 5)]
+                    .into_iter()
+                    .filter(move |_| self.turns > 0),
+            )
+        }
+        fn heuristic(&self) -> Pair {
+            self.lead
+        }
+        fn execute_move(&mut self, next_move: &Self::Move) {
+            self.lead = self.lead + self.to_move.perspective(*next_move);
+            self.turns -= 1;
+            self.to_move = self.to_move.opponent();
+        }
+        fn to_move(&self) -> Player {
+            self.to_move
+        }
+        fn outcome(&self) -> Outcome {
+            match (self.turns, self.lead.0) {
+                (0, lead) if lead >= 2 => Outcome::Win,
+                (0, lead) if lead <= -2 => Outcome::Loss,
+                _ => Outcome::Ongoing,
+            }
+        }
+        fn position_key(&self) -> Option<u64> {
+            Some(((self.lead.0 as u64) << 32) ^ ((self.lead.1 as u64) << 8) ^ self.turns as u64)
+        }
+    }
+
+    #[test]
+    fn fixed_depth_finds_win_test() {
+        let game = nim(5);
+        assert_eq!(minimax_search(&game, 5), Some(1));
+    }
+
+    #[test]
+    fn timed_finds_win_test() {
+        let game = nim(7);
+        let next_move = minimax_search_timed(&game, 20, Duration::from_secs(10));
+        assert_eq!(next_move, Some(3));
+    }
+
+    #[test]
+    fn timed_matches_fixed_depth_test() {
+        let game = nim(10);
+        assert_eq!(
+            minimax_search_timed(&game, 4, Duration::from_secs(10)),
+            minimax_search(&game, 4)
+        );
+    }
+
+    #[test]
+    fn timed_out_still_moves_test() {
+        let game = nim(30);
+        let next_move = minimax_search_timed(&game, 30, Duration::from_secs(0));
+        assert!(next_move.is_some());
+    }
+
+    #[test]
+    fn table_matches_plain_search_test() {
+        let mut searcher = Searcher::new();
+        let mut plain = Searcher::with_table_size(0);
+        // Only winning piles have a single right answer, every move loses from the others
+        for pile in (1..15).filter(|pile| pile % 4 != 0) {
+            let game = nim(pile);
+            assert_eq!(searcher.search(&game, pile), Some(pile % 4));
+            assert_eq!(plain.search(&game, pile), Some(pile % 4));
+            let game = Nim {
+                pile,
+                to_move: Player::Min,
+            };
+            assert_eq!(searcher.search(&game, pile), Some(pile % 4));
+        }
+        assert!(!searcher.table().is_empty());
+        assert!(plain.table().is_empty());
+    }
+
+    #[test]
+    fn table_reused_across_calls_test() {
+        let mut searcher = Searcher::new();
+        searcher.search(&nim(12), 12);
+        let entry = searcher
+            .table()
+            .probe(8 ^ MIN_TO_MOVE_KEY)
+            .expect("position searched on the previous call");
+        assert!(entry.best_move.is_some());
+        assert_eq!(searcher.search(&nim(9), 9), Some(1));
+    }
+
+    #[test]
+    fn undo_matches_clone_test() {
+        let mut undoing = Searcher::with_table_size(0);
+        let mut cloning = Searcher::with_table_size(0).with_config(SearchConfig {
+            undo_moves: false,
+            ..SearchConfig::default()
+        });
+        for pile in 1..12 {
+            let undone = undoing.analyze(&nim(pile), pile);
+            let cloned = cloning.analyze(&nim(pile), pile);
+            assert_eq!(undone.best_move, cloned.best_move);
+            assert_eq!(undone.score, cloned.score);
+            assert_eq!(undone.principal_variation, cloned.principal_variation);
+            assert_eq!(undone.nodes, cloned.nodes);
+        }
+    }
+
+    #[test]
+    fn parallel_matches_single_thread_test() {
+        for pile in 1..15 {
+            let single = minimax_analyze(&nim(pile), pile);
+            for threads in [1, 2, 4] {
+                let config = SearchConfig {
+                    threads,
+                    ..SearchConfig::default()
+                };
+                let parallel = Searcher::new()
+                    .with_config(config)
+                    .analyze_parallel(&nim(pile), pile);
+                assert_eq!(parallel.score, single.score);
+                assert_eq!(parallel.principal_variation[0], parallel.best_move.unwrap());
+            }
+        }
+        assert_eq!(minimax_search_parallel(&nim(7), 7, 3), Some(3));
+    }
+
+    #[test]
+    fn parallel_timed_test() {
+        let mut searcher = Searcher::new().with_config(SearchConfig {
+            threads: 2,
+            ..SearchConfig::default()
+        });
+        let result = searcher.analyze_parallel_timed(&nim(6), 20, Duration::from_secs(10));
+        assert_eq!(result.best_move, Some(2));
+        assert!(result.depth >= 6);
+        assert!(result.nodes > 0);
+        let result = searcher.analyze_parallel_timed(&nim(30), 30, Duration::from_secs(0));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn pvs_matches_plain_test() {
+        for pile in 1..15 {
+            let plain = SearchConfig {
+                pvs: false,
+                ..SearchConfig::default()
+            };
+            let expected = Searcher::new().with_config(plain).analyze(&nim(pile), pile);
+            let result = Searcher::new().analyze(&nim(pile), pile);
+            assert_eq!(result.score, expected.score);
+            assert_eq!(result.best_move, expected.best_move);
+        }
+    }
+
+    #[test]
+    fn aspiration_matches_full_window_test() {
+        for pile in 1..15 {
+            let expected = Searcher::new().analyze_timed(&nim(pile), pile, Duration::from_secs(10));
+            for threads in [1, 2] {
+                let config = SearchConfig {
+                    aspiration_window: Some(1),
+                    threads,
+                    ..SearchConfig::default()
+                };
+                let mut searcher = Searcher::new().with_config(config);
+                let result = searcher.analyze_timed(&nim(pile), pile, Duration::from_secs(10));
+                assert_eq!(result.score, expected.score);
+                assert_eq!(result.depth, expected.depth);
+                let result =
+                    searcher.analyze_parallel_timed(&nim(pile), pile, Duration::from_secs(10));
+                assert_eq!(result.score, expected.score);
+            }
+        }
+    }
+
+    #[test]
+    fn mtdf_matches_plain_test() {
+        for pile in 1..15 {
+            let expected = minimax_analyze(&nim(pile), pile);
+            for size in [0, 1 << 10] {
+                let result = Searcher::with_table_size(size).analyze_mtdf(&nim(pile), pile);
+                assert_eq!(result.score, expected.score);
+                assert_eq!(
+                    result.principal_variation.first(),
+                    result.best_move.as_ref()
+                );
+            }
+            let result =
+                Searcher::new().analyze_mtdf_timed(&nim(pile), pile, Duration::from_secs(10));
+            assert_eq!(result.score, expected.score);
+        }
+        assert_eq!(minimax_search_mtdf(&nim(7), 7), Some(3));
+    }
+
+    #[test]
+    fn alpha_beta_bounds_test() {
+        let mut searcher = Searcher::with_table_size(0);
+        let exact = searcher.alpha_beta(&nim(5), 5, -WIN_SCORE, WIN_SCORE);
+        assert_eq!(exact, WIN_SCORE - 3);
+        // A window below the score fails high with a lower bound, one above fails low
+        assert!(searcher.alpha_beta(&nim(5), 5, -1, 0) >= 0);
+        assert!(searcher.alpha_beta(&nim(5), 5, WIN_SCORE - 2, WIN_SCORE) <= WIN_SCORE - 2);
+        // Whatever is taken from a pile of four, the other player takes the rest
+        assert_eq!(
+            searcher.alpha_beta(&nim(4), 5, -WIN_SCORE, WIN_SCORE),
+            -(WIN_SCORE - 2)
+        );
+    }
+
+    #[test]
+    fn selective_search_test() {
+        let selective = [
+            SearchConfig {
+                null_move: true,
+                ..SearchConfig::default()
+            },
+            SearchConfig {
+                late_move_reductions: true,
+                ..SearchConfig::default()
+            },
+            SearchConfig {
+                futility_margin: Some(5),
+                ..SearchConfig::default()
+            },
+        ];
+        for turns in [6, 7, 8] {
+            let mut plain = Searcher::with_table_size(0);
+            let expected = plain.analyze(&race(turns), turns);
+            // Max gets one more move than Min with an odd number of turns
+            assert_eq!(expected.score, 5 * (turns % 2) as isize);
+            for config in selective.iter() {
+                let mut searcher = Searcher::with_table_size(0).with_config(config.clone());
+                let result = searcher.analyze(&race(turns), turns);
+                assert_eq!(result.score, expected.score);
+                assert_eq!(result.best_move, Some(5));
+                assert!(result.nodes < expected.nodes);
+            }
+        }
+    }
+
+    #[test]
+    fn multi_pv_test() {
+        for turns in [3, 4] {
+            let result = minimax_analyze_multi_pv(&race(turns), turns, 3);
+            let best = 5 * (turns % 2) as isize;
+            assert_eq!(result.score, best);
+            assert_eq!(result.best_move, Some(5));
+            // Every step not taken now is a step behind at the end
+            let ranked: Vec<(isize, isize)> = result
+                .ranked_moves
+                .iter()
+                .map(|line| (line.next_move, line.score))
+                .collect();
+            assert_eq!(ranked, vec![(5, best), (4, best - 1), (3, best - 2)]);
+            for line in result.ranked_moves.iter() {
+                assert_eq!(line.principal_variation[0], line.next_move);
+                assert_eq!(line.principal_variation.len(), turns);
+            }
+            assert_eq!(
+                result.principal_variation,
+                result.ranked_moves[0].principal_variation
+            );
+        }
+    }
+
+    #[test]
+    fn multi_pv_matches_plain_test() {
+        let mut searcher = Searcher::new();
+        for pile in 1..12 {
+            let expected = minimax_analyze(&nim(pile), pile);
+            let result = searcher.analyze_multi_pv(&nim(pile), pile, 5);
+            assert_eq!(result.score, expected.score);
+            assert_eq!(result.ranked_moves.len(), pile.min(3));
+            assert_eq!(result.ranked_moves[0].score, expected.score);
+            let timed =
+                searcher.analyze_multi_pv_timed(&nim(pile), pile, Duration::from_secs(10), 5);
+            let scores = |result: &super::SearchResult<usize>| {
+                result
+                    .ranked_moves
+                    .iter()
+                    .map(|line| line.score)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(scores(&timed), scores(&result));
+        }
+        assert!(minimax_analyze_multi_pv(&nim(0), 3, 2)
+            .ranked_moves
+            .is_empty());
+        assert!(minimax_analyze(&nim(5), 5).ranked_moves.is_empty());
+    }
+
+    // The moves a policy plays in a position, with one seed after the other
+    fn played<G>(game: &G, depth: usize, policy: RootPolicy, threads: usize) -> Vec<G::Move>
+    where
+        G: HeuristicGameTree + Send + Sync,
+        G::Move: Send + Sync,
+    {
+        let config = SearchConfig {
+            policy,
+            threads,
+            ..SearchConfig::default()
+        };
+        (0..40)
+            .map(|seed| {
+                let mut searcher = Searcher::new()
+                    .with_config(config.clone())
+                    .with_rng(Rng::new(seed));
+                let result = match threads {
+                    1 => searcher.analyze(game, depth),
+                    _ => searcher.analyze_parallel(game, depth),
+                };
+                assert!(result.principal_variation.first() == result.best_move.as_ref());
+                result.best_move.unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn random_best_test() {
+        // Every move from a pile of four loses just as fast
+        for threads in [1, 2] {
+            let moves = played(&nim(4), 4, RootPolicy::RandomBest, threads);
+            assert_eq!(moves.iter().collect::<HashSet<_>>().len(), 3);
+            assert_eq!(moves, played(&nim(4), 4, RootPolicy::RandomBest, threads));
+            let result = Searcher::new()
+                .with_config(SearchConfig {
+                    policy: RootPolicy::RandomBest,
+                    threads,
+                    ..SearchConfig::default()
+                })
+                .analyze(&nim(4), 4);
+            assert_eq!(result.ranked_moves.len(), 3);
+            assert_eq!(result.score, -(WIN_SCORE - 2));
+            // Only one move wins from a pile of five
+            assert!(played(&nim(5), 5, RootPolicy::RandomBest, threads)
+                .iter()
+                .all(|m| *m == 1));
+        }
+        assert!(played(&nim(4), 4, RootPolicy::Best, 1)
+            .iter()
+            .all(|m| *m == 1));
+    }
+
+    #[test]
+    fn softmax_test() {
+        let cold = played(&race(1), 1, RootPolicy::Softmax { temperature: 0.0 }, 1);
+        assert!(cold.iter().all(|m| *m == 5));
+        let hot = played(&race(1), 1, RootPolicy::Softmax { temperature: 1e9 }, 1);
+        assert_eq!(hot.iter().collect::<HashSet<_>>().len(), 5);
+        // A step further is e times as likely, so the best move comes up most often
+        let warm = played(&race(1), 1, RootPolicy::Softmax { temperature: 1.0 }, 2);
+        let count = |step| warm.iter().filter(|m| **m == step).count();
+        assert!(count(5) > count(4) && count(4) > count(1));
+        assert_eq!(
+            warm,
+            played(&race(1), 1, RootPolicy::Softmax { temperature: 1.0 }, 2)
+        );
+        let result = Searcher::new()
+            .with_config(SearchConfig {
+                policy: RootPolicy::Softmax { temperature: 1e9 },
+                ..SearchConfig::default()
+            })
+            .analyze(&race(1), 1);
+        assert_eq!(result.score, 5);
+        assert_eq!(result.ranked_moves.len(), 5);
+    }
+
+    #[test]
+    fn epsilon_greedy_test() {
+        let greedy = played(&nim(5), 5, RootPolicy::EpsilonGreedy { epsilon: 0.0 }, 1);
+        assert!(greedy.iter().all(|m| *m == 1));
+        let random = played(&nim(5), 5, RootPolicy::EpsilonGreedy { epsilon: 1.0 }, 1);
+        assert_eq!(random.iter().collect::<HashSet<_>>().len(), 3);
+        let some = played(&nim(5), 5, RootPolicy::EpsilonGreedy { epsilon: 0.5 }, 1);
+        let best = some.iter().filter(|m| **m == 1).count();
+        assert!(best > 10 && best < 40);
+        assert_eq!(
+            some,
+            played(&nim(5), 5, RootPolicy::EpsilonGreedy { epsilon: 0.5 }, 1)
+        );
+    }
+
+    #[test]
+    fn node_budget_test() {
+        let first = minimax_analyze_nodes(&race(40), 40, 5_000);
+        let again = minimax_analyze_nodes(&race(40), 40, 5_000);
+        assert!(first.nodes <= 5_001);
+        assert!(first.depth > 0 && first.depth < 40);
+        assert_eq!(first.best_move, Some(5));
+        assert_eq!(
+            (first.depth, first.score, first.nodes),
+            (again.depth, again.score, again.nodes)
+        );
+        assert_eq!(first.principal_variation, again.principal_variation);
+        // A bigger budget gets further
+        let bigger = minimax_analyze_nodes(&race(40), 40, 50_000);
+        assert!(bigger.depth > first.depth);
+        assert_eq!(minimax_search_nodes(&nim(7), 20, 100_000), Some(3));
+        // Without a single completed depth, the best of the moves searched so far will do
+        // rather than the first legal move
+        let starved = minimax_analyze_nodes(&race(40), 40, 1);
+        assert_eq!((starved.depth, starved.best_move), (0, Some(5)));
+    }
+
+    #[test]
+    fn stop_test() {
+        // Far too big to search in full without a table
+        let mut searcher = Searcher::with_table_size(0);
+        let stop = searcher.stop_flag();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            stop.store(true, Ordering::Relaxed);
+        });
+        let result = searcher.analyze_timed(&race(40), 40, Duration::from_secs(600));
+        stopper.join().unwrap();
+        assert!(result.elapsed < Duration::from_secs(60));
+        assert_eq!(result.best_move, Some(5));
+        assert!(result.depth > 0 && result.depth < 40);
+        // The flag was cleared for the next search
+        assert!(!searcher.stop_flag().load(Ordering::Relaxed));
+        assert_eq!(searcher.analyze(&race(4), 4).score, 0);
+    }
+
+    #[test]
+    fn stop_fixed_depth_test() {
+        // Stopped before the search even starts, a search to a fixed depth still moves
+        let mut searcher = Searcher::with_table_size(0);
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        let result = searcher.analyze(&race(40), 40);
+        assert!(result.best_move.is_some());
+        assert_eq!(result.depth, 0);
+        assert!(!searcher.stop_flag().load(Ordering::Relaxed));
+        // Stopped after the first move, which the flag is only checked often enough to catch
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        let result = searcher.analyze(&race(8), 6);
+        assert_eq!(result.depth, 5);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn stop_parallel_test() {
+        // Same as stop_fixed_depth_test, the moves the threads finished before the stop are kept
+        let mut searcher = Searcher::with_table_size(0).with_config(SearchConfig {
+            threads: 1,
+            ..SearchConfig::default()
+        });
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        let result = searcher.analyze_parallel(&race(8), 6);
+        assert_eq!(result.depth, 5);
+        assert_eq!(result.best_move, Some(5));
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        let result = searcher.analyze_parallel_timed(&race(8), 6, Duration::from_secs(10));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn progress_test() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+        let mut searcher =
+            Searcher::new().on_progress(move |progress: &super::SearchResult<usize>| {
+                let line = progress.principal_variation.clone();
+                sink.lock()
+                    .unwrap()
+                    .push((progress.depth, progress.score, progress.nodes, line));
+            });
+        let result = searcher.analyze_timed(&nim(7), 7, Duration::from_secs(10));
+        let deepening = reports.lock().unwrap().clone();
+        let depths: Vec<usize> = deepening.iter().map(|report| report.0).collect();
+        assert_eq!(depths, (1..=result.depth).collect::<Vec<_>>());
+        let last = deepening.last().unwrap();
+        assert_eq!((last.1, last.2), (result.score, result.nodes));
+        assert_eq!(last.3, result.principal_variation);
+        assert!(deepening.windows(2).all(|pair| pair[0].2 <= pair[1].2));
+        // A search to a fixed depth completes a single depth
+        searcher.analyze(&nim(5), 3);
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), deepening.len() + 1);
+        assert_eq!(reports.last().unwrap().0, 3);
+    }
+
+    #[test]
+    fn null_move_needs_pass_test() {
+        // Nim cannot pass, so null-move pruning leaves the search as it was
+        let config = SearchConfig {
+            null_move: true,
+            ..SearchConfig::default()
+        };
+        for pile in 1..12 {
+            let expected = minimax_analyze(&nim(pile), pile);
+            let result = Searcher::new()
+                .with_config(config.clone())
+                .analyze(&nim(pile), pile);
+            assert_eq!(result.score, expected.score);
+            assert_eq!(result.nodes, expected.nodes);
+        }
+    }
+
+    #[test]
+    fn analyze_test() {
+        let result = minimax_analyze(&nim(5), 5);
+        assert_eq!(result.best_move, Some(1));
+        // Three moves to win, however the other player goes about it
+        assert_eq!(result.score, WIN_SCORE - 3);
+        assert_eq!(result.depth, 5);
+        assert_eq!(result.principal_variation[0], 1);
+        // The expected line of play takes the whole pile
+        assert_eq!(result.principal_variation.iter().sum::<usize>(), 5);
+        assert!(result.nodes > 0);
+        assert!(result.cutoffs > 0);
+    }
+
+    #[test]
+    fn analyze_timed_test() {
+        let result = minimax_analyze_timed(&nim(6), 20, Duration::from_secs(10));
+        assert_eq!(result.best_move, Some(2));
+        assert_eq!(result.principal_variation.iter().sum::<usize>(), 6);
+        assert!(result.depth >= 6);
+    }
+
+    #[test]
+    fn analyze_no_moves_test() {
+        let result = minimax_analyze(&nim(0), 3);
+        assert!(result.best_move.is_none());
+        assert!(result.principal_variation.is_empty());
+        assert_eq!(result.score, -WIN_SCORE);
+    }
+
+    #[test]
+    fn losing_side_test() {
+        // Every move loses, but the search still sees how long it can hold out
+        let result = minimax_analyze(&nim(8), 8);
+        assert_eq!(result.score, -(WIN_SCORE - 4));
+        assert!(result.principal_variation.len() >= 4);
+    }
+
+    #[test]
+    fn beam_test() {
+        // Every position of nim looks even, so a beam of one only ever tries taking one stone
+        assert_eq!(minimax_search_beam(&nim(6), 6, 1), Some(1));
+        assert_eq!(minimax_search_beam(&nim(6), 6, 3), Some(2));
+        // Going further always looks best in a race, and is
+        let full = minimax_analyze(&race(6), 6);
+        let beam = minimax_analyze_beam(&race(6), 6, 2);
+        assert_eq!(beam.best_move, Some(5));
+        assert_eq!(beam.score, full.score);
+        assert!(beam.nodes < full.nodes);
+        // Back to full width afterwards, without trusting the scores of the beam
+        let mut searcher = Searcher::new();
+        assert_eq!(searcher.search_beam(&nim(6), 6, 1), Some(1));
+        assert!(searcher.table().is_empty());
+        assert_eq!(searcher.search(&nim(6), 6), Some(2));
+        assert!(!searcher.table().is_empty());
+    }
+
+    #[test]
+    fn lexicographic_score_test() {
+        let start = |turns| StyleRace {
+            lead: Pair(0, 0),
+            turns,
+            to_move: Player::Max,
+        };
+        // The longest step first, and the most stylish of the longest steps
+        let result = minimax_analyze(&start(2), 1);
+        assert_eq!(result.best_move, Some(Pair(2, 5)));
+        assert_eq!(result.score, Pair(2, 5));
+        let result = minimax_analyze(&start(2), 2);
+        assert_eq!(result.score, Pair(0, 0));
+        // Two long steps against one win on the third ply, as the table remembers too
+        let mut searcher = Searcher::new();
+        for _ in 0..2 {
+            let result = searcher.analyze(&start(3), 3);
+            assert_eq!(result.score, Pair::WIN - Pair::steps(3));
+            assert_eq!(result.best_move.map(|step| step.0), Some(2));
+        }
+        let config = SearchConfig {
+            aspiration_window: Some(2),
+            ..SearchConfig::default()
+        };
+        let result = Searcher::new().with_config(config).analyze_timed(
+            &start(3),
+            3,
+            Duration::from_secs(10),
+        );
+        assert_eq!(result.score, Pair::WIN - Pair::steps(3));
     }
 }
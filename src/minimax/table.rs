@@ -0,0 +1,168 @@
+/// What a stored score says about the true value of a position.
+///
+/// Alpha-beta only learns the exact value of a position when the value lands inside the
+/// search window. Otherwise the search stopped early, and the score is only a bound.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Bound {
+    /// The score is the value of the position
+    Exact,
+    /// The value of the position is at least the score (the search failed high)
+    Lower,
+    /// The value of the position is at most the score (the search failed low)
+    Upper,
+}
+
+/// A position that has already been searched, remembered by its position key.
+#[derive(Clone, Debug)]
+pub struct TableEntry<M, S = isize> {
+    /// The position key, used to tell apart positions sharing a slot
+    pub key: u64,
+    /// How many plies were searched below the position
+    pub depth: usize,
+    /// The heuristic the search found for the position
+    pub score: S,
+    /// Whether the score is exact or only a bound
+    pub bound: Bound,
+    /// The move that produced the score, tried first the next time the position comes up
+    pub best_move: Option<M>,
+}
+
+/// A fixed-size cache of searched positions, so that a position reached through a different
+/// move order does not need to be searched again.
+///
+/// Positions are placed in a slot picked by their key. A different position always replaces
+/// the one in its slot, so entries from moves long past do not linger. The same position is
+/// only replaced by a search that went at least as deep.
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::minimax::{Bound, TranspositionTable};
+/// let mut table: TranspositionTable<usize> = TranspositionTable::new(1024);
+/// table.store(42, 3, 7, Bound::Exact, Some(2));
+/// let entry = table.probe(42).unwrap();
+/// assert_eq!(entry.score, 7);
+/// assert_eq!(entry.best_move, Some(2));
+/// assert!(table.probe(43).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TranspositionTable<M, S = isize> {
+    slots: Vec<Option<TableEntry<M, S>>>,
+    capacity: usize,
+}
+
+impl<M: Clone, S: Copy> TranspositionTable<M, S> {
+    /// Creates an empty table that holds at most `capacity` positions.
+    /// The memory is only claimed once the first position is stored.
+    pub fn new(capacity: usize) -> Self {
+        TranspositionTable {
+            slots: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the stored entry for the position with this key, if any.
+    pub fn probe(&self, key: u64) -> Option<&TableEntry<M, S>> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        self.slots[self.slot(key)]
+            .as_ref()
+            .filter(|entry| entry.key == key)
+    }
+
+    /// Remembers the result of searching the position with this key.
+    pub fn store(&mut self, key: u64, depth: usize, score: S, bound: Bound, best_move: Option<M>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.slots.is_empty() {
+            self.slots.resize(self.capacity, None);
+        }
+        let slot = self.slot(key);
+        let keep_old = match &self.slots[slot] {
+            Some(old) => old.key == key && old.depth > depth,
+            None => false,
+        };
+        if !keep_old {
+            self.slots[slot] = Some(TableEntry {
+                key,
+                depth,
+                score,
+                bound,
+                best_move,
+            });
+        }
+    }
+
+    /// Forgets every stored position.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// Returns the number of positions currently stored.
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Returns whether no positions are stored.
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|slot| slot.is_none())
+    }
+
+    /// Returns the most positions the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key % self.capacity as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod table_tests {
+    use super::{Bound, TranspositionTable};
+
+    #[test]
+    fn empty_probe_test() {
+        let table: TranspositionTable<usize> = TranspositionTable::new(16);
+        assert!(table.probe(3).is_none());
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn collision_test() {
+        let mut table = TranspositionTable::new(16);
+        table.store(3, 1, 5, Bound::Lower, Some(0));
+        table.store(19, 1, 6, Bound::Exact, Some(1));
+        assert!(table.probe(3).is_none());
+        assert_eq!(table.probe(19).unwrap().score, 6);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn deeper_entry_kept_test() {
+        let mut table = TranspositionTable::new(16);
+        table.store(3, 4, 5, Bound::Exact, Some(0));
+        table.store(3, 2, 6, Bound::Exact, Some(1));
+        assert_eq!(table.probe(3).unwrap().depth, 4);
+        table.store(3, 5, 7, Bound::Upper, None);
+        assert_eq!(table.probe(3).unwrap().bound, Bound::Upper);
+    }
+
+    #[test]
+    fn other_position_replaces_deeper_test() {
+        let mut table = TranspositionTable::new(16);
+        table.store(3, 6, 5, Bound::Exact, Some(0));
+        table.store(19, 1, 6, Bound::Upper, Some(1));
+        assert!(table.probe(3).is_none());
+        assert_eq!(table.probe(19).unwrap().depth, 1);
+    }
+
+    #[test]
+    fn zero_capacity_test() {
+        let mut table = TranspositionTable::new(0);
+        table.store(3, 4, 5, Bound::Exact, Some(0));
+        assert!(table.probe(3).is_none());
+    }
+}
@@ -0,0 +1,420 @@
+use super::Score;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// A trait for games of any number of players, such as Chinese checkers or Connect-N for three.
+///
+/// HeuristicGameTree scores a position with a single number that one player wants high and the
+/// other low, which only works when there are two players. Here every player has a score of
+/// their own instead: the heuristic is a vector with one score per player, and every player
+/// wants their own score as high as possible. Players are numbered from 0, and the game
+/// decides whose turn it is, so turns do not need to go round in order.
+///
+/// # How can I implement MultiPlayerGame?
+/// MultiPlayerGame requires the Move and Score types, and the players, possible_moves, heuristic,
+/// execute_move, to_move methods to be implemented.
+/// The winner method is optional, but without it the search cannot tell a won game from a good
+/// position.
+pub trait MultiPlayerGame: Clone {
+    type Move: Clone + Eq + Hash;
+    /// The type of the score of a single player, isize for most games
+    type Score: Score;
+
+    /// How many players there are. Players are numbered from 0 to one less than this
+    fn players(&self) -> usize;
+
+    /// All the possible moves of the player to move.
+    /// Returns a box of an iterator, where all the items are possible moves
+    fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_>;
+
+    /// The score of every player, in order: the higher a player's score, the better the
+    /// position is for that player. Scores should stay well away from Score::WIN, which the
+    /// search keeps for won and lost games
+    fn heuristic(&self) -> Vec<Self::Score>;
+
+    /// The game is updated with the move of the player to move, and the turn passes on
+    fn execute_move(&mut self, next_move: &Self::Move);
+
+    /// The number of the player whose turn it is
+    fn to_move(&self) -> usize;
+
+    /// The player who won, once the game is over. A won game scores WIN for the winner, less
+    /// a step per ply so that faster wins are preferred, and the negation for everyone else.
+    /// A game that is over without a winner should have no possible moves, and is scored by
+    /// its heuristic.
+    /// Returns None by default
+    fn winner(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Everything a multi-player search found out about a position.
+#[derive(Clone, Debug)]
+pub struct MultiSearchResult<M, S = isize> {
+    /// The best move for the player to move, or None if there are no possible moves
+    pub best_move: Option<M>,
+    /// The score of every player at the end of the principal variation
+    pub scores: Vec<S>,
+    /// The line of play the search expects, starting with the best move
+    pub principal_variation: Vec<M>,
+    /// How many moves ahead the search looked
+    pub depth: usize,
+    /// How many positions the search visited
+    pub nodes: u64,
+    /// How long the search took
+    pub elapsed: Duration,
+}
+
+/// Function: Max^n search, the generalization of minimax to any number of players.
+/// Every player picks the move that leads to the highest score of their own, and the score
+/// vector of that move is passed up as it is. Unlike minimax, the players are not simply each
+/// other's opponents, so alpha-beta pruning does not carry over: only a player who can win on
+/// the spot stops looking at other moves. When two moves score the same for the player to
+/// move, the first one is picked.
+///
+/// # Arguments
+/// * `depth` - A usize that holds how many moves ahead to look, counting the moves of every player
+///
+/// Returns the best move for the player to move
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::multiplayer::{maxn_search, MultiPlayerGame};
+/// // Three players take turns taking 1 or 2 stones, and whoever takes the last stone wins
+/// #[derive(Clone)]
+/// struct Pile(usize, usize);
+/// impl MultiPlayerGame for Pile {
+///     type Move = usize;
+///     type Score = isize;
+///     fn players(&self) -> usize { 3 }
+///     fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+///         Box::new((1..=2).filter(move |take| *take <= self.0))
+///     }
+///     fn heuristic(&self) -> Vec<isize> { vec![0; 3] }
+///     fn execute_move(&mut self, take: &Self::Move) {
+///         self.0 -= take;
+///         self.1 = (self.1 + 1) % 3;
+///     }
+///     fn to_move(&self) -> usize { self.1 }
+///     fn winner(&self) -> Option<usize> {
+///         // The player who took the last stone moved just before
+///         (self.0 == 0).then(|| (self.1 + 2) % 3)
+///     }
+/// }
+///
+/// assert_eq!(maxn_search(&Pile(2, 0), 3), Some(2));
+/// ```
+pub fn maxn_search<G>(game: &G, depth: usize) -> Option<G::Move>
+where
+    G: MultiPlayerGame,
+{
+    maxn_analyze(game, depth).best_move
+}
+
+/// Same as `maxn_search`, but returns everything the search found out.
+pub fn maxn_analyze<G>(game: &G, depth: usize) -> MultiSearchResult<G::Move, G::Score>
+where
+    G: MultiPlayerGame,
+{
+    MultiSearch::default().analyze(game, depth, |search, game, depth| {
+        search.maxn(game, depth, 0)
+    })
+}
+
+/// Function: Paranoid search, which assumes that every other player is out to get the player
+/// to move.
+/// The other players are treated as a single opponent who always picks the move that is worst
+/// for the player to move, whatever it does to their own scores. This turns the game into a
+/// two-player game, which is searched with alpha-beta pruning on the score of the player to
+/// move alone, and so looks much deeper than max^n in the same time. The price is that the
+/// player gives up on moves that only go wrong if everyone else plays against them.
+///
+/// # Arguments
+/// * `depth` - A usize that holds how many moves ahead to look, counting the moves of every player
+///
+/// Returns the best move for the player to move
+pub fn paranoid_search<G>(game: &G, depth: usize) -> Option<G::Move>
+where
+    G: MultiPlayerGame,
+{
+    paranoid_analyze(game, depth).best_move
+}
+
+/// Same as `paranoid_search`, but returns everything the search found out.
+pub fn paranoid_analyze<G>(game: &G, depth: usize) -> MultiSearchResult<G::Move, G::Score>
+where
+    G: MultiPlayerGame,
+{
+    let root = game.to_move();
+    MultiSearch::default().analyze(game, depth, |search, game, depth| {
+        search.paranoid(
+            game,
+            depth,
+            0,
+            root,
+            -G::Score::INFINITY,
+            G::Score::INFINITY,
+        )
+    })
+}
+
+// The score vector a line of play leads to, with the moves of the line from last to first
+type Line<G> = (
+    Vec<<G as MultiPlayerGame>::Score>,
+    Vec<<G as MultiPlayerGame>::Move>,
+);
+
+#[derive(Default)]
+struct MultiSearch {
+    nodes: u64,
+}
+
+impl MultiSearch {
+    fn analyze<G, F>(
+        &mut self,
+        game: &G,
+        depth: usize,
+        search: F,
+    ) -> MultiSearchResult<G::Move, G::Score>
+    where
+        G: MultiPlayerGame,
+        F: FnOnce(&mut MultiSearch, &G, usize) -> Line<G>,
+    {
+        let started = Instant::now();
+        let (scores, mut line) = if depth == 0 {
+            // Choose first available move.
+            (
+                evaluate(game, 0),
+                game.possible_moves().next().into_iter().collect(),
+            )
+        } else {
+            search(self, game, depth)
+        };
+        line.reverse();
+        MultiSearchResult {
+            best_move: line.first().cloned(),
+            scores,
+            principal_variation: line,
+            depth,
+            nodes: self.nodes,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    fn maxn<G: MultiPlayerGame>(&mut self, game: &G, depth: usize, ply: usize) -> Line<G> {
+        self.nodes += 1;
+        if depth == 0 || game.winner().is_some() {
+            return (evaluate(game, ply), Vec::new());
+        }
+        let player = game.to_move();
+        // Nothing beats winning with the very next move
+        let fastest_win = G::Score::WIN - G::Score::steps(ply + 1);
+        let mut best: Option<Line<G>> = None;
+        for mymove in game.possible_moves() {
+            let mut next_state = game.clone();
+            next_state.execute_move(&mymove);
+            let (scores, mut line) = self.maxn(&next_state, depth - 1, ply + 1);
+            if best
+                .as_ref()
+                .is_none_or(|best| scores[player] > best.0[player])
+            {
+                line.push(mymove);
+                best = Some((scores, line));
+            }
+            if best
+                .as_ref()
+                .is_some_and(|best| best.0[player] >= fastest_win)
+            {
+                break;
+            }
+        }
+        // No possible moves, the position speaks for itself
+        best.unwrap_or_else(|| (evaluate(game, ply), Vec::new()))
+    }
+
+    // Fail-soft alpha-beta on the score of the root player, who is the only one maximizing
+    fn paranoid<G: MultiPlayerGame>(
+        &mut self,
+        game: &G,
+        depth: usize,
+        ply: usize,
+        root: usize,
+        mut alpha: G::Score,
+        mut beta: G::Score,
+    ) -> Line<G> {
+        self.nodes += 1;
+        if depth == 0 || game.winner().is_some() {
+            return (evaluate(game, ply), Vec::new());
+        }
+        let maximizing = game.to_move() == root;
+        let mut best: Option<Line<G>> = None;
+        for mymove in game.possible_moves() {
+            let mut next_state = game.clone();
+            next_state.execute_move(&mymove);
+            let (scores, mut line) =
+                self.paranoid(&next_state, depth - 1, ply + 1, root, alpha, beta);
+            let score = scores[root];
+            let better = best.as_ref().is_none_or(|best| {
+                if maximizing {
+                    score > best.0[root]
+                } else {
+                    score < best.0[root]
+                }
+            });
+            if better {
+                line.push(mymove);
+                best = Some((scores, line));
+            }
+            if maximizing {
+                alpha = alpha.max(score);
+            } else {
+                beta = beta.min(score);
+            }
+            if beta <= alpha {
+                break;
+            }
+        }
+        // No possible moves, the position speaks for itself
+        best.unwrap_or_else(|| (evaluate(game, ply), Vec::new()))
+    }
+}
+
+// The score vector of a position, where a finished game counts as won by the winner and lost
+// by everyone else, `ply` moves away from the root
+fn evaluate<G: MultiPlayerGame>(game: &G, ply: usize) -> Vec<G::Score> {
+    match game.winner() {
+        Some(winner) => {
+            let win = G::Score::WIN - G::Score::steps(ply);
+            (0..game.players())
+                .map(|player| if player == winner { win } else { -win })
+                .collect()
+        }
+        None => game.heuristic(),
+    }
+}
+
+#[cfg(test)]
+mod multiplayer_tests {
+    use super::{maxn_analyze, maxn_search, paranoid_analyze, paranoid_search, MultiPlayerGame};
+    use crate::Score;
+
+    // A game tree written out by hand: player 0 picks a branch, then player 1 picks a leaf,
+    // and the leaves hold the score vectors
+    #[derive(Clone)]
+    struct Tree {
+        path: Vec<usize>,
+    }
+
+    const LEAVES: [[[isize; 3]; 2]; 2] = [[[6, 5, 0], [0, 4, 6]], [[3, 3, 3], [2, 4, 4]]];
+
+    impl MultiPlayerGame for Tree {
+        type Move = usize;
+        type Score = isize;
+        fn players(&self) -> usize {
+            3
+        }
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            Box::new((0..2).filter(move |_| self.path.len() < 2))
+        }
+        fn heuristic(&self) -> Vec<isize> {
+            match self.path[..] {
+                [branch, leaf] => LEAVES[branch][leaf].to_vec(),
+                _ => vec![0; 3],
+            }
+        }
+        fn execute_move(&mut self, next_move: &Self::Move) {
+            self.path.push(*next_move);
+        }
+        fn to_move(&self) -> usize {
+            self.path.len() % 3
+        }
+    }
+
+    // Three players take turns taking 1 or 2 stones, and whoever takes the last stone wins
+    #[derive(Clone)]
+    struct Pile {
+        stones: usize,
+        to_move: usize,
+    }
+
+    impl MultiPlayerGame for Pile {
+        type Move = usize;
+        type Score = isize;
+        fn players(&self) -> usize {
+            3
+        }
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            Box::new((1..=2).filter(move |take| *take <= self.stones))
+        }
+        fn heuristic(&self) -> Vec<isize> {
+            vec![0; 3]
+        }
+        fn execute_move(&mut self, take: &Self::Move) {
+            self.stones -= take;
+            self.to_move = (self.to_move + 1) % 3;
+        }
+        fn to_move(&self) -> usize {
+            self.to_move
+        }
+        fn winner(&self) -> Option<usize> {
+            (self.stones == 0).then_some((self.to_move + 2) % 3)
+        }
+    }
+
+    #[test]
+    fn maxn_test() {
+        // Player 1 takes the leaf that is best for player 1, which leaves player 0 the 6
+        let result = maxn_analyze(&Tree { path: Vec::new() }, 2);
+        assert_eq!(result.best_move, Some(0));
+        assert_eq!(result.scores, vec![6, 5, 0]);
+        assert_eq!(result.principal_variation, vec![0, 0]);
+        assert_eq!(result.nodes, 7);
+    }
+
+    #[test]
+    fn paranoid_test() {
+        // Player 1 is assumed to take the leaf that is worst for player 0, so the safe branch wins
+        let result = paranoid_analyze(&Tree { path: Vec::new() }, 2);
+        assert_eq!(result.best_move, Some(1));
+        assert_eq!(result.scores, vec![2, 4, 4]);
+        assert_eq!(result.principal_variation, vec![1, 1]);
+    }
+
+    #[test]
+    fn win_test() {
+        let game = Pile {
+            stones: 2,
+            to_move: 1,
+        };
+        assert_eq!(maxn_search(&game, 3), Some(2));
+        assert_eq!(paranoid_search(&game, 3), Some(2));
+        let result = maxn_analyze(&game, 3);
+        assert_eq!(
+            result.scores,
+            vec![-(isize::WIN - 1), isize::WIN - 1, -(isize::WIN - 1)]
+        );
+        // The player to move cannot stop the next player from taking the last stone
+        let result = paranoid_analyze(
+            &Pile {
+                stones: 3,
+                to_move: 0,
+            },
+            3,
+        );
+        assert_eq!(result.scores[0], -(isize::WIN - 2));
+    }
+
+    #[test]
+    fn depth_zero_test() {
+        let result = maxn_analyze(
+            &Pile {
+                stones: 5,
+                to_move: 0,
+            },
+            0,
+        );
+        assert_eq!(result.best_move, Some(1));
+        assert_eq!(result.scores, vec![0; 3]);
+        assert_eq!(result.nodes, 0);
+    }
+}
@@ -0,0 +1,356 @@
+use super::{HeuristicGameTree, Outcome, Player};
+use std::time::{Duration, Instant};
+
+// The proof number of a position that can never be proven, and the disproof number of one that
+// can never be disproven
+const INFINITY: u64 = u64::MAX;
+
+/// Function: Proof-number search, which proves wins and losses instead of judging positions.
+/// Proof-number search grows a tree of the game to find out whether the player to move can
+/// force a win. Every position keeps a proof number, the fewest positions still to be settled
+/// to prove the win, and a disproof number, the fewest to rule it out. The search always grows
+/// the tree at the position that counts towards both (the most proving position), so it goes
+/// straight for the narrow lines where the proof is quickest, however deep they are.
+///
+/// The search only needs `HeuristicGameTree::possible_moves`, `execute_move`, `to_move` and
+/// `outcome`: it never looks at the heuristic. A position that is not over but has no moves
+/// counts as won by nobody. When the win is ruled out, a second search tries to prove the loss;
+/// a position where both are ruled out is a draw, but the search does not tell that apart from
+/// a position it ran out of room for.
+///
+/// # Arguments
+/// * `max_nodes` - A usize that holds the most positions the search tree may hold. The tree is
+///   kept in memory, so this also caps how much memory the search takes.
+///
+/// Returns a ProofResult, with what could be proven for the player to move
+///
+/// # Example
+/// Countdown is the game of the `Searcher` example (see `minimax::Searcher`).
+/// ```
+/// # use heuristic_game_tree::{HeuristicGameTree, Outcome, Player};
+/// # use heuristic_game_tree::pns::{pns_solve, Proof};
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/doc/countdown.rs"));
+/// # fn main() {
+/// // A multiple of three is lost for the player to move
+/// let result = pns_solve(&Countdown(7, Player::Max), 1000);
+/// assert_eq!((result.proof, result.winning_move), (Proof::Win, Some(1)));
+/// assert_eq!(pns_solve(&Countdown(6, Player::Min), 1000).proof, Proof::Loss);
+/// # }
+/// ```
+pub fn pns_solve<G>(game: &G, max_nodes: usize) -> ProofResult<G::Move>
+where
+    G: HeuristicGameTree,
+{
+    let started = Instant::now();
+    let player = game.to_move();
+    let win = ProofTree::prove(game, player, max_nodes);
+    if win.proven() {
+        return ProofResult {
+            proof: Proof::Win,
+            winning_move: win.proving_move(),
+            proof_size: win.proof_size(),
+            nodes: win.nodes.len(),
+            elapsed: started.elapsed(),
+        };
+    }
+    let searched = win.nodes.len();
+    // The tree of the first search is of no use to the second, so it makes room for it
+    drop(win);
+    let loss = ProofTree::prove(game, player.opponent(), max_nodes);
+    let (proof, proof_size) = if loss.proven() {
+        (Proof::Loss, loss.proof_size())
+    } else {
+        (Proof::DrawOrUnknown, 0)
+    };
+    ProofResult {
+        proof,
+        winning_move: None,
+        proof_size,
+        nodes: searched + loss.nodes.len(),
+        elapsed: started.elapsed(),
+    }
+}
+
+/// What proof-number search proved about a position, for the player to move.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Proof {
+    /// The player to move can force a win, whatever the other player does
+    Win,
+    /// The other player can force a win, whatever the player to move does
+    Loss,
+    /// Neither could be proven: the game is a draw with best play, or the search ran out of room
+    DrawOrUnknown,
+}
+
+/// Everything a proof-number search found out about a position.
+#[derive(Clone, Debug)]
+pub struct ProofResult<M> {
+    /// What could be proven for the player to move
+    pub proof: Proof,
+    /// A move that keeps the win in hand, when the player to move is proven to win
+    pub winning_move: Option<M>,
+    /// How many positions the proof takes: every reply of the losing player, but only one
+    /// move of the winning player, down to finished games. 0 when nothing was proven
+    pub proof_size: usize,
+    /// How many positions the search trees held in all
+    pub nodes: usize,
+    /// How long the search took
+    pub elapsed: Duration,
+}
+
+// A position in the proof tree
+struct Node<G: HeuristicGameTree> {
+    // The move that leads here from the parent
+    last_move: Option<G::Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // When the player out to prove the win is to move, a single child proves the position,
+    // otherwise it takes every child
+    attacker_to_move: bool,
+    proof: u64,
+    disproof: u64,
+    // The position itself, only kept until its children are added
+    game: Option<G>,
+}
+
+// The tree of a search that tries to prove that the attacker wins
+struct ProofTree<G: HeuristicGameTree> {
+    nodes: Vec<Node<G>>,
+    attacker: Player,
+}
+
+impl<G: HeuristicGameTree> ProofTree<G> {
+    // Grows the tree until the root is proven or disproven, or the tree is full
+    fn prove(game: &G, attacker: Player, max_nodes: usize) -> Self {
+        let mut tree = ProofTree {
+            nodes: Vec::new(),
+            attacker,
+        };
+        tree.add(game.clone(), None, None);
+        while tree.nodes[0].proof != 0 && tree.nodes[0].disproof != 0 {
+            let node = tree.most_proving();
+            let game = tree.nodes[node]
+                .game
+                .take()
+                .expect("only positions that go on are expanded");
+            let moves: Vec<G::Move> = game.possible_moves().collect();
+            if tree.nodes.len() + moves.len() > max_nodes {
+                break;
+            }
+            for next_move in moves {
+                let mut child = game.clone();
+                child.execute_move(&next_move);
+                let child = tree.add(child, Some(next_move), Some(node));
+                tree.nodes[node].children.push(child);
+            }
+            tree.update(node);
+        }
+        tree
+    }
+
+    // Adds a position to the tree, proven or disproven already if the game is over
+    fn add(&mut self, game: G, last_move: Option<G::Move>, parent: Option<usize>) -> usize {
+        let outcome = game.outcome();
+        let (proof, disproof) =
+            if outcome == Outcome::Ongoing && game.possible_moves().next().is_some() {
+                (1, 1)
+            } else if self.wins(outcome) {
+                (0, INFINITY)
+            } else {
+                (INFINITY, 0)
+            };
+        self.nodes.push(Node {
+            last_move,
+            parent,
+            children: Vec::new(),
+            attacker_to_move: game.to_move() == self.attacker,
+            proof,
+            disproof,
+            game: (proof == 1).then_some(game),
+        });
+        self.nodes.len() - 1
+    }
+
+    fn wins(&self, outcome: Outcome) -> bool {
+        matches!(
+            (outcome, self.attacker),
+            (Outcome::Win, Player::Max) | (Outcome::Loss, Player::Min)
+        )
+    }
+
+    // Follows the children that count towards the proof or disproof of the root, down to a
+    // position without children
+    fn most_proving(&self) -> usize {
+        let mut node = 0;
+        while !self.nodes[node].children.is_empty() {
+            let children = self.nodes[node].children.iter().copied();
+            let next = if self.nodes[node].attacker_to_move {
+                children.min_by_key(|&child| self.nodes[child].proof)
+            } else {
+                children.min_by_key(|&child| self.nodes[child].disproof)
+            };
+            node = next.expect("the position has children");
+        }
+        node
+    }
+
+    // Brings the proof and disproof numbers of a position and its ancestors up to date
+    fn update(&mut self, mut node: usize) {
+        loop {
+            let children = &self.nodes[node].children;
+            let proofs = children.iter().map(|&child| self.nodes[child].proof);
+            let disproofs = children.iter().map(|&child| self.nodes[child].disproof);
+            // One child is enough to prove a position where the attacker moves, and to disprove
+            // one where the defender moves, the other takes all of them
+            let (proof, disproof) = if self.nodes[node].attacker_to_move {
+                (
+                    proofs.min().unwrap_or(INFINITY),
+                    disproofs.fold(0, u64::saturating_add),
+                )
+            } else {
+                (
+                    proofs.fold(0, u64::saturating_add),
+                    disproofs.min().unwrap_or(0),
+                )
+            };
+            self.nodes[node].proof = proof;
+            self.nodes[node].disproof = disproof;
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => return,
+            }
+        }
+    }
+
+    fn proven(&self) -> bool {
+        self.nodes[0].proof == 0
+    }
+
+    // A move of the root that is proven to win
+    fn proving_move(&self) -> Option<G::Move> {
+        self.nodes[0]
+            .children
+            .iter()
+            .find(|&&child| self.nodes[child].proof == 0)
+            .and_then(|&child| self.nodes[child].last_move.clone())
+    }
+
+    // The number of positions in the proof of the root
+    fn proof_size(&self) -> usize {
+        let mut size = 0;
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            size += 1;
+            let children = &self.nodes[node].children;
+            if self.nodes[node].attacker_to_move {
+                stack.extend(children.iter().find(|&&child| self.nodes[child].proof == 0));
+            } else {
+                stack.extend(children);
+            }
+        }
+        size
+    }
+}
+
+#[cfg(test)]
+mod pns_tests {
+    use super::{pns_solve, Proof};
+    use crate::test_games::{nim, Nim};
+    use crate::{HeuristicGameTree, Outcome, Player};
+
+    // Both players pass a counter back and forth, and nobody ever wins
+    #[derive(Clone)]
+    struct Stalemate(usize, Player);
+
+    impl HeuristicGameTree for Stalemate {
+        type Move = usize;
+        type Score = isize;
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            Box::new((1..=2).filter(move |_| self.0 > 0))
+        }
+        fn heuristic(&self) -> isize {
+            0
+        }
+        fn execute_move(&mut self, _next_move: &Self::Move) {
+            self.0 -= 1;
+            self.1 = self.1.opponent();
+        }
+        fn to_move(&self) -> Player {
+            self.1
+        }
+        fn outcome(&self) -> Outcome {
+            match self.0 {
+                0 => Outcome::Draw,
+                _ => Outcome::Ongoing,
+            }
+        }
+    }
+
+    #[test]
+    fn win_test() {
+        for player in [Player::Max, Player::Min] {
+            for pile in (1..12).filter(|pile| pile % 4 != 0) {
+                let result = pns_solve(
+                    &Nim {
+                        pile,
+                        to_move: player,
+                    },
+                    100_000,
+                );
+                assert_eq!(result.proof, Proof::Win);
+                assert_eq!(result.winning_move, Some(pile % 4));
+                assert!(result.proof_size > 0 && result.proof_size <= result.nodes);
+            }
+        }
+    }
+
+    #[test]
+    fn loss_test() {
+        for player in [Player::Max, Player::Min] {
+            for pile in [4, 8, 12] {
+                let result = pns_solve(
+                    &Nim {
+                        pile,
+                        to_move: player,
+                    },
+                    100_000,
+                );
+                assert_eq!(result.proof, Proof::Loss);
+                assert_eq!(result.winning_move, None);
+                assert!(result.proof_size > 0);
+            }
+        }
+        // Taking one, two or three stones from four leaves a pile that is taken at once
+        assert_eq!(pns_solve(&nim(4), 100_000).proof_size, 7);
+    }
+
+    #[test]
+    fn draw_test() {
+        let result = pns_solve(&Stalemate(6, Player::Max), 100_000);
+        assert_eq!(result.proof, Proof::DrawOrUnknown);
+        assert_eq!(result.proof_size, 0);
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn finished_game_test() {
+        // Max took the last stone, so Min has lost already
+        let result = pns_solve(
+            &Nim {
+                pile: 0,
+                to_move: Player::Min,
+            },
+            10,
+        );
+        assert_eq!((result.proof, result.proof_size), (Proof::Loss, 1));
+        assert_eq!(result.winning_move, None);
+    }
+
+    #[test]
+    fn node_cap_test() {
+        let result = pns_solve(&nim(13), 50);
+        assert_eq!(result.proof, Proof::DrawOrUnknown);
+        assert!(result.nodes <= 100);
+        assert_eq!(pns_solve(&nim(13), 100_000).proof, Proof::Win);
+    }
+}
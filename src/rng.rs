@@ -0,0 +1,98 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small, fast, seedable random number generator (SplitMix64).
+///
+/// The same seed always produces the same numbers, on every machine, which keeps anything built
+/// on it reproducible. It is not suitable for cryptography.
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::rng::Rng;
+/// let mut a = Rng::new(7);
+/// let mut b = Rng::new(7);
+/// assert_eq!(a.next_u64(), b.next_u64());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator whose numbers are determined by `seed`.
+    pub const fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Returns the next random number, uniformly spread over all u64 values.
+    pub const fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Creates a generator seeded from the system clock, for when every run should differ.
+    pub fn from_clock() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Rng::new(now)
+    }
+
+    /// Returns a random number from 0 up to, but not including, 1, uniformly spread.
+    pub fn next_f64(&mut self) -> f64 {
+        // A double holds 53 bits of precision, the rest would be rounded away unevenly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a random number from 0 up to, but not including, `n`.
+    /// `n` must not be 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        // Scaling the whole range down, rather than taking a remainder, keeps every number
+        // equally likely
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+#[cfg(test)]
+mod rng_tests {
+    use super::Rng;
+
+    #[test]
+    fn same_seed_test() {
+        let mut a = Rng::new(1);
+        let mut b = Rng::new(1);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn below_test() {
+        let mut rng = Rng::new(3);
+        let mut seen = [false; 5];
+        for _ in 0..100 {
+            let n = rng.below(5);
+            assert!(n < 5);
+            seen[n] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+    }
+
+    #[test]
+    fn next_f64_test() {
+        let mut rng = Rng::new(5);
+        let numbers: Vec<f64> = (0..1000).map(|_| rng.next_f64()).collect();
+        assert!(numbers.iter().all(|n| (0.0..1.0).contains(n)));
+        let mean = numbers.iter().sum::<f64>() / numbers.len() as f64;
+        assert!((mean - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn different_seed_test() {
+        let mut a = Rng::new(1);
+        let mut b = Rng::new(2);
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+}
@@ -0,0 +1,441 @@
+use super::{HeuristicGameTree, Outcome, Player, Score};
+use crate::minimax::{side_key, MAX_WIN_PLIES};
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+// The first bytes of a tablebase file
+const MAGIC: &[u8; 4] = b"HGTB";
+
+// The longest distance a stored value can hold
+const MAX_DISTANCE: usize = (u16::MAX as usize - 2) / 2;
+
+/// The outcome of a position under best play by both players, from the point of view of the
+/// player to move, with how many plies it takes for the game to end. The winner takes the
+/// fastest way to the win, and the loser holds out as long as they can.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum Value {
+    /// The player to move wins
+    Win(usize),
+    /// The player to move loses
+    Loss(usize),
+    /// Neither player can force a win
+    Draw,
+}
+
+impl Value {
+    /// Returns the score the search gives the value, for a position `ply` moves away from the
+    /// root: a win or loss that many plies further away, as the search scores finished games.
+    /// The search only tells wins from heuristics up to about a thousand plies from the root,
+    /// so a win further away than that scores as a win right at the limit.
+    pub fn score<S: Score>(self, ply: usize) -> S {
+        let steps = |distance: usize| S::steps((ply + distance).min(MAX_WIN_PLIES - 1));
+        match self {
+            Value::Win(distance) => S::WIN - steps(distance),
+            Value::Loss(distance) => -(S::WIN - steps(distance)),
+            Value::Draw => S::ZERO,
+        }
+    }
+
+    // The value for the other player
+    fn flip(self) -> Value {
+        match self {
+            Value::Win(distance) => Value::Loss(distance),
+            Value::Loss(distance) => Value::Win(distance),
+            Value::Draw => Value::Draw,
+        }
+    }
+
+    fn encode(self) -> u16 {
+        match self {
+            Value::Draw => 0,
+            Value::Win(distance) => 1 + 2 * distance.min(MAX_DISTANCE) as u16,
+            Value::Loss(distance) => 2 + 2 * distance.min(MAX_DISTANCE) as u16,
+        }
+    }
+
+    fn decode(code: u16) -> Value {
+        match code {
+            0 => Value::Draw,
+            code if code % 2 == 1 => Value::Win(code as usize / 2),
+            code => Value::Loss(code as usize / 2 - 1),
+        }
+    }
+}
+
+/// An endgame tablebase: the exact outcome of every position of a small part of a game, such
+/// as the checkers positions with at most four pieces, computed by retrograde analysis.
+///
+/// Retrograde analysis works backwards from the end of the game. The positions where the game
+/// is over are known first. A position with a move to a position lost for the player who moves
+/// next is won; a position whose moves all lead to positions won for the player who moves next
+/// is lost; and from those the analysis works its way back, one ply at a time, until nothing
+/// changes. The positions left over are draws, where both players can keep the game going.
+///
+/// Positions are told apart by `HeuristicGameTree::position_key` and the player to move, so
+/// the game has to provide keys. The table keeps only the keys and values, in 10 bytes a
+/// position, and can be saved to a file and loaded back (see `save` and `load`). A Searcher
+/// probes it before scoring a position by its heuristic (see `Searcher::with_tablebase`).
+///
+/// # Example
+/// Countdown is the game of the `Searcher` example (see `minimax::Searcher`).
+/// ```
+/// # use heuristic_game_tree::{HeuristicGameTree, Outcome, Player};
+/// # use heuristic_game_tree::tablebase::{Tablebase, Value};
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/doc/countdown.rs"));
+/// # fn main() {
+/// let states = (0..10).flat_map(|count| [Countdown(count, Player::Max), Countdown(count, Player::Min)]);
+/// let tablebase = Tablebase::build(states);
+/// // A multiple of three is lost for the player to move
+/// assert_eq!(tablebase.probe(&Countdown(6, Player::Max)), Some(Value::Loss(4)));
+/// assert_eq!(tablebase.probe(&Countdown(7, Player::Min)), Some(Value::Win(5)));
+/// assert_eq!(tablebase.probe(&Countdown(12, Player::Max)), None);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tablebase {
+    // Sorted by key
+    entries: Vec<(u64, u16)>,
+}
+
+impl Tablebase {
+    /// Function: Retrograde analysis of the given positions.
+    /// The positions need to take in every position their moves lead to, or the analysis
+    /// cannot be sure of their outcome: a position that depends on the outcome of a position
+    /// outside of them is left out of the table, and so is a position without moves where the
+    /// game goes on. Positions without a position key are skipped, and a position given twice
+    /// only counts once.
+    ///
+    /// # Arguments
+    /// * `states` - Every position of the part of the game to analyze, with either player to move
+    ///
+    /// Returns the tablebase of the positions whose outcome is known
+    pub fn build<G, I>(states: I) -> Tablebase
+    where
+        G: HeuristicGameTree,
+        I: IntoIterator<Item = G>,
+    {
+        let mut keys = Vec::new();
+        let mut games = Vec::new();
+        let mut index = HashMap::new();
+        for state in states {
+            if let Some(key) = side_key(&state) {
+                index.entry(key).or_insert_with(|| {
+                    keys.push(key);
+                    games.push(state);
+                    games.len() - 1
+                });
+            }
+        }
+        // Every position the moves of a position lead to, turned around, so that a position
+        // knows which positions lead to it, and whether by a move of the same player
+        let mut parents: Vec<Vec<(usize, bool)>> = vec![Vec::new(); games.len()];
+        let mut open_children = vec![0usize; games.len()];
+        let mut values: Vec<Option<Value>> = vec![None; games.len()];
+        let mut unknown = vec![false; games.len()];
+        let mut solved = VecDeque::new();
+        for (i, game) in games.iter().enumerate() {
+            let player = game.to_move();
+            match game.outcome() {
+                Outcome::Ongoing => {}
+                Outcome::Draw => {
+                    values[i] = Some(Value::Draw);
+                    continue;
+                }
+                outcome => {
+                    let won = (outcome == Outcome::Win) == (player == Player::Max);
+                    values[i] = Some(if won { Value::Win(0) } else { Value::Loss(0) });
+                    solved.push_back(i);
+                    continue;
+                }
+            }
+            for mymove in game.possible_moves() {
+                let mut next_state = game.clone();
+                next_state.execute_move(&mymove);
+                match side_key(&next_state).and_then(|key| index.get(&key)) {
+                    Some(&child) => parents[child].push((i, next_state.to_move() == player)),
+                    None => unknown[i] = true,
+                }
+                open_children[i] += 1;
+            }
+            // Without moves, only the heuristic can tell
+            unknown[i] |= open_children[i] == 0;
+        }
+        while let Some(child) = solved.pop_front() {
+            let value = values[child].unwrap();
+            for &(parent, same_player) in &parents[child] {
+                if values[parent].is_some() {
+                    continue;
+                }
+                match if same_player { value } else { value.flip() } {
+                    Value::Win(distance) => {
+                        values[parent] = Some(Value::Win(distance + 1));
+                        solved.push_back(parent);
+                    }
+                    Value::Loss(distance) => {
+                        open_children[parent] -= 1;
+                        if open_children[parent] == 0 {
+                            // The last move to be refuted is the one that holds out longest
+                            values[parent] = Some(Value::Loss(distance + 1));
+                            solved.push_back(parent);
+                        }
+                    }
+                    Value::Draw => {}
+                }
+            }
+        }
+        // A position left open is a draw, unless it can reach a position of unknown outcome
+        // without either player being able to force anything on the way
+        let mut spread: Vec<usize> = (0..games.len())
+            .filter(|&i| unknown[i] && values[i].is_none())
+            .collect();
+        while let Some(child) = spread.pop() {
+            for &(parent, _) in &parents[child] {
+                if values[parent].is_none() && !unknown[parent] {
+                    unknown[parent] = true;
+                    spread.push(parent);
+                }
+            }
+        }
+        let mut entries: Vec<(u64, u16)> = keys
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, key)| match values[i] {
+                Some(value) => Some((key, value.encode())),
+                None if !unknown[i] => Some((key, Value::Draw.encode())),
+                None => None,
+            })
+            .collect();
+        entries.sort_unstable();
+        Tablebase { entries }
+    }
+
+    /// Returns the outcome of the position, or None if it is not in the table.
+    pub fn probe<G: HeuristicGameTree>(&self, game: &G) -> Option<Value> {
+        side_key(game).and_then(|key| self.probe_key(key))
+    }
+
+    /// Same as `probe`, but for the key of a position, with the player to move mixed in as
+    /// the search does.
+    pub fn probe_key(&self, key: u64) -> Option<Value> {
+        self.entries
+            .binary_search_by_key(&key, |entry| entry.0)
+            .ok()
+            .map(|i| Value::decode(self.entries[i].1))
+    }
+
+    /// Returns the number of positions in the table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the table has no positions.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the table: the bytes "HGTB", the number of positions as 8 bytes, and then every
+    /// position as its key in 8 bytes and its value in 2, all little-endian.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for (key, value) in &self.entries {
+            writer.write_all(&key.to_le_bytes())?;
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a table written by `write`.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Tablebase> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a tablebase",
+            ));
+        }
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count);
+        let mut entries = Vec::new();
+        let mut entry = [0; 10];
+        for _ in 0..count {
+            reader.read_exact(&mut entry)?;
+            let key = u64::from_le_bytes(entry[..8].try_into().unwrap());
+            let value = u16::from_le_bytes(entry[8..].try_into().unwrap());
+            entries.push((key, value));
+        }
+        if !entries.is_sorted_by_key(|entry| entry.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "tablebase keys out of order",
+            ));
+        }
+        Ok(Tablebase { entries })
+    }
+
+    /// Saves the table to a file, see `write`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Loads a table saved with `save`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Tablebase> {
+        Tablebase::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tablebase_tests {
+    use super::{Tablebase, Value};
+    use crate::minimax::{minimax_analyze, Searcher, MAX_WIN_PLIES, WIN_SCORE};
+    use crate::test_games::{nim, Nim};
+    use crate::{HeuristicGameTree, Outcome, Player};
+    use std::sync::Arc;
+
+    fn nims(piles: std::ops::Range<usize>) -> impl Iterator<Item = Nim> {
+        piles.flat_map(|pile| [Player::Max, Player::Min].map(|to_move| Nim { pile, to_move }))
+    }
+
+    // A token on a ring of four squares, moved one square either way or left where it is.
+    // Whoever moves it onto square 0 wins, and the token may only wait on square 2.
+    #[derive(Clone)]
+    struct Ring {
+        square: usize,
+        to_move: Player,
+    }
+
+    impl HeuristicGameTree for Ring {
+        type Move = usize;
+        type Score = isize;
+        fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+            let wait = (self.square == 2).then_some(2);
+            Box::new(
+                vec![(self.square + 1) % 4, (self.square + 3) % 4]
+                    .into_iter()
+                    .chain(wait),
+            )
+        }
+        fn heuristic(&self) -> isize {
+            0
+        }
+        fn execute_move(&mut self, next_move: &Self::Move) {
+            self.square = *next_move;
+            self.to_move = self.to_move.opponent();
+        }
+        fn to_move(&self) -> Player {
+            self.to_move
+        }
+        fn outcome(&self) -> Outcome {
+            match (self.square, self.to_move) {
+                (0, Player::Max) => Outcome::Loss,
+                (0, Player::Min) => Outcome::Win,
+                _ => Outcome::Ongoing,
+            }
+        }
+        fn position_key(&self) -> Option<u64> {
+            Some(self.square as u64)
+        }
+    }
+
+    #[test]
+    fn nim_test() {
+        let tablebase = Tablebase::build(nims(0..20));
+        assert_eq!(tablebase.len(), 40);
+        for game in nims(0..20) {
+            // A multiple of four is lost, in two plies for every four stones
+            let expected = match game.pile % 4 {
+                0 => Value::Loss(game.pile / 2),
+                _ => Value::Win(game.pile / 4 * 2 + 1),
+            };
+            assert_eq!(tablebase.probe(&game), Some(expected));
+        }
+    }
+
+    #[test]
+    fn long_distance_test() {
+        let tablebase = Tablebase::build(nims(0..2200));
+        let value = tablebase.probe(&nim(2100)).unwrap();
+        assert_eq!(value, Value::Loss(1050));
+        // Still scored as a lost game, not as a heuristic
+        let limit = WIN_SCORE - (MAX_WIN_PLIES as isize - 1);
+        assert_eq!(value.score::<isize>(3), -limit);
+        assert_eq!(Value::Win(5000).score::<isize>(0), limit);
+        assert_eq!(Value::Win(10).score::<isize>(2), WIN_SCORE - 12);
+    }
+
+    #[test]
+    fn draw_test() {
+        let rings = (0..4)
+            .flat_map(|square| [Player::Max, Player::Min].map(|to_move| Ring { square, to_move }));
+        let tablebase = Tablebase::build(rings);
+        let probe = |square| {
+            tablebase.probe(&Ring {
+                square,
+                to_move: Player::Min,
+            })
+        };
+        assert_eq!(probe(0), Some(Value::Loss(0)));
+        assert_eq!(probe(1), Some(Value::Win(1)));
+        assert_eq!(probe(3), Some(Value::Win(1)));
+        // Moving off square 2 loses, so both players wait there for ever
+        assert_eq!(probe(2), Some(Value::Draw));
+    }
+
+    #[test]
+    fn unknown_test() {
+        // Piles of 10 and 11 can take stones into piles outside of the table
+        let tablebase = Tablebase::build(nims(9..12));
+        assert_eq!(
+            tablebase.probe(&Nim {
+                pile: 11,
+                to_move: Player::Max
+            }),
+            None
+        );
+        assert_eq!(
+            tablebase.probe(&Nim {
+                pile: 5,
+                to_move: Player::Max
+            }),
+            None
+        );
+        assert!(tablebase.is_empty());
+    }
+
+    #[test]
+    fn file_test() {
+        let tablebase = Tablebase::build(nims(0..20));
+        let mut bytes = Vec::new();
+        tablebase.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 4 + 8 + 40 * 10);
+        assert_eq!(Tablebase::read(&bytes[..]).unwrap(), tablebase);
+        assert!(Tablebase::read(&bytes[1..]).is_err());
+        assert!(Tablebase::read(&bytes[..bytes.len() - 1]).is_err());
+        let path = std::env::temp_dir().join(format!("tablebase_test_{}.hgtb", std::process::id()));
+        tablebase.save(&path).unwrap();
+        let loaded = Tablebase::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), tablebase);
+    }
+
+    #[test]
+    fn search_test() {
+        let tablebase = Arc::new(Tablebase::build(nims(0..13)));
+        let game = Nim {
+            pile: 14,
+            to_move: Player::Max,
+        };
+        // Every position looks even to a shallow search, but the table knows better
+        assert_eq!(Searcher::new().search(&game, 1), Some(1));
+        let mut searcher = Searcher::new().with_tablebase(tablebase);
+        let result = searcher.analyze(&game, 1);
+        assert_eq!(result.best_move, Some(2));
+        assert_eq!(result.score, minimax_analyze(&game, 14).score);
+        assert_eq!(result.score, WIN_SCORE - 7);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{HeuristicGameTree, Outcome, Player};
+
+/// Take 1 to 3 stones from a pile, whoever takes the last stone wins. The tests of the search
+/// modules play it, since every position's value is known: a multiple of four is lost for the
+/// player to move.
+#[derive(Clone)]
+pub(crate) struct Nim {
+    pub(crate) pile: usize,
+    pub(crate) to_move: Player,
+}
+
+/// A pile of Nim with Max to move
+pub(crate) fn nim(pile: usize) -> Nim {
+    Nim {
+        pile,
+        to_move: Player::Max,
+    }
+}
+
+impl HeuristicGameTree for Nim {
+    type Move = usize;
+    type Score = isize;
+    fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
+        Box::new((1..=3).filter(move |take| *take <= self.pile))
+    }
+    fn heuristic(&self) -> isize {
+        0
+    }
+    fn execute_move(&mut self, next_move: &Self::Move) {
+        self.pile -= next_move;
+        self.to_move = self.to_move.opponent();
+    }
+    fn to_move(&self) -> Player {
+        self.to_move
+    }
+    fn can_undo(&self) -> bool {
+        true
+    }
+    fn undo_move(&mut self, last_move: &Self::Move) {
+        self.pile += last_move;
+        self.to_move = self.to_move.opponent();
+    }
+    fn outcome(&self) -> Outcome {
+        // Whoever took the last stone won, so the player to move lost
+        match (self.pile, self.to_move) {
+            (0, Player::Max) => Outcome::Loss,
+            (0, Player::Min) => Outcome::Win,
+            _ => Outcome::Ongoing,
+        }
+    }
+    fn position_key(&self) -> Option<u64> {
+        Some(self.pile as u64)
+    }
+}
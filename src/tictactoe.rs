@@ -1,9 +1,19 @@
 use super::*;
+use crate::book::{BookBuilder, OpeningBook};
+use crate::general_game::computer_searcher;
 use crate::general_game::print_piece;
+use crate::general_game::think_time;
+use crate::general_game::Engine;
 use crate::general_game::Piece;
-use crate::minimax;
+use crate::general_game::{book_move, BOOK_PLIES};
+use crate::general_game::{node_budget, MAX_DEPTH};
+use crate::mcts::Mcts;
+use crate::rng::Rng;
+use crate::zobrist::ZobristTable;
 use std::io::{stdin, stdout, Write};
 
+static ZOBRIST: ZobristTable<9, 2> = ZobristTable::new(0x7469_6374_6163);
+
 const TICWINS: [[usize; 3]; 8] = [
     [0, 1, 2],
     [0, 3, 6],
@@ -15,11 +25,12 @@ const TICWINS: [[usize; 3]; 8] = [
     [6, 7, 8],
 ];
 
-impl<'a> HeuristicGameTree for TicGame {
+impl HeuristicGameTree for TicGame {
     type Move = usize;
+    type Score = isize;
     //type Heuristic = isize;
     // fn possible_moves(&self) -> Iterator<Item = Self::Move> {
-    fn possible_moves(&self) -> Box<Iterator<Item = Self::Move> + '_> {
+    fn possible_moves(&self) -> Box<dyn Iterator<Item = Self::Move> + '_> {
         let mut list = Vec::new();
         if !self.check_win(Piece::X).0 && !self.check_win(Piece::O).0 {
             for i in 0..9 {
@@ -32,16 +43,60 @@ impl<'a> HeuristicGameTree for TicGame {
         Box::new(list.into_iter())
     }
 
-    fn heuristic(&self) -> isize {
+    fn heuristic(&self) -> Self::Score {
         // invariant: x_streak != o_streak != 3
         // keep track of best streak by each player
         let x_streak = self.clone().check_win(Piece::X).1;
         let o_streak = self.clone().check_win(Piece::O).1;
         o_streak - x_streak
     }
-    fn execute_move(&mut self, next_move: &Self::Move, is_opponent: bool) -> bool {
-        self.store_move(*next_move, if is_opponent { Piece::O } else { Piece::X });
-        !is_opponent
+    fn execute_move(&mut self, next_move: &Self::Move) {
+        let piece = match self.to_move() {
+            Player::Max => Piece::O,
+            Player::Min => Piece::X,
+        };
+        self.store_move(*next_move, piece);
+    }
+    fn to_move(&self) -> Player {
+        // X goes first, so it is X's turn whenever both have played as often
+        let x_count = self.board.iter().filter(|p| **p == Some(Piece::X)).count();
+        let o_count = self.board.iter().filter(|p| **p == Some(Piece::O)).count();
+        if x_count == o_count {
+            Player::Min
+        } else {
+            Player::Max
+        }
+    }
+    fn outcome(&self) -> Outcome {
+        // O is the computer agent, so O is Max
+        if self.check_win(Piece::O).0 {
+            Outcome::Win
+        } else if self.check_win(Piece::X).0 {
+            Outcome::Loss
+        } else if !self.board_not_full() {
+            Outcome::Draw
+        } else {
+            Outcome::Ongoing
+        }
+    }
+    fn position_key(&self) -> Option<u64> {
+        Some(self.hash)
+    }
+    fn can_undo(&self) -> bool {
+        true
+    }
+    fn undo_move(&mut self, last_move: &Self::Move) {
+        if let Some(old) = self.board[*last_move].take() {
+            self.hash ^= ZOBRIST.piece(*last_move, old.index());
+        }
+    }
+    fn move_priority(&self, next_move: &Self::Move) -> isize {
+        // The center is on four winning lines, the corners on three, the edges on two
+        match next_move {
+            4 => 2,
+            0 | 2 | 6 | 8 => 1,
+            _ => 0,
+        }
     }
 }
 //------------------------------------TicGame-----------------------------------------
@@ -50,6 +105,7 @@ impl<'a> HeuristicGameTree for TicGame {
 struct TicGame {
     board: [Option<Piece>; 9],
     winner: Option<Piece>,
+    hash: u64,
 }
 
 impl<'a> TicGame {
@@ -57,6 +113,7 @@ impl<'a> TicGame {
         TicGame {
             board: [None; 9],
             winner: None,
+            hash: 0,
         }
     }
 
@@ -102,7 +159,11 @@ impl<'a> TicGame {
     }
 
     fn store_move(&mut self, position: usize, player: Piece) {
+        if let Some(old) = self.board[position] {
+            self.hash ^= ZOBRIST.piece(position, old.index());
+        }
         self.board[position] = Some(player);
+        self.hash ^= ZOBRIST.piece(position, player.index());
     }
 
     fn check_win(&self, player: Piece) -> (bool, isize) {
@@ -132,18 +193,36 @@ impl<'a> TicGame {
     }
 }
 
+/// Function: Builds an opening book for Tic-tac-toe by self-play, see `general_game::build_book`.
+/// The searches that choose the moves go 9 plies deep, to the end of every game.
+/// # Arguments
+/// * `games` - How many games to play against itself
+pub fn book_tic(games: usize) -> OpeningBook {
+    let mut builder = BookBuilder::new(BOOK_PLIES, 9).with_rng(Rng::from_clock());
+    builder.self_play(&TicGame::new(), games);
+    builder.build()
+}
+
 /// Starts the Tic-tac-toe game
 ///
 /// # Arguments
 ///
 /// * `difficulty` - A usize that holds the difficulty of the game. This value is passed as
-///                  an argument to minimax search, determining the depth of the minimax search
-///                  tree (i.e. the number of steps ahead that the AI agent should look ahead when
-///                  determining its move)
-pub fn start_tic(difficulty: usize) {
+///   an argument to minimax search, determining the depth of the minimax search
+///   tree (i.e. the number of steps ahead that the AI agent should look ahead when
+///   determining its move)
+///
+/// * `engine` - An Engine that holds how the computer agent chooses its moves
+///
+/// * `book` - The opening book the minimax engine plays its first moves from
+pub fn start_tic(difficulty: usize, engine: Engine, book: &OpeningBook) {
     let mut new_game = TicGame::new();
+    let mut searcher = computer_searcher();
+    let mut mcts = Mcts::new(Rng::from_clock());
+    // Picks between the moves of the opening book
+    let mut rng = Rng::from_clock();
 
-    while new_game.winner == None && new_game.board_not_full() {
+    while new_game.winner.is_none() && new_game.board_not_full() {
         println!("Where do you want to put your X? Input format: row(space)column e.g. A 1");
         new_game.printboard();
         let mut loc = String::new();
@@ -162,7 +241,13 @@ pub fn start_tic(difficulty: usize) {
                 if new_game.check_win(Piece::X).0 {
                     new_game.winner = Some(Piece::X);
                 } else {
-                    let next_move = minimax::minimax_search(&new_game, difficulty * 3, true);
+                    let next_move = match engine {
+                        Engine::Minimax => book_move(book, &new_game, difficulty, &mut rng)
+                            .or_else(|| {
+                                searcher.search_nodes(&new_game, MAX_DEPTH, node_budget(difficulty))
+                            }),
+                        Engine::MonteCarlo => mcts.search_timed(&new_game, think_time(difficulty)),
+                    };
                     if let Some(m) = next_move {
                         new_game.store_move(m, Piece::O);
                         if new_game.check_win(Piece::O).0 {
@@ -189,9 +274,16 @@ pub fn start_tic(difficulty: usize) {
 
 #[cfg(test)]
 mod tic_tests {
+    use super::book_tic;
     use super::print_piece;
     use super::Piece;
     use super::TicGame;
+    use super::ZOBRIST;
+    use crate::general_game::book_move;
+    use crate::mcts::mcts_search;
+    use crate::minimax::{minimax_analyze, minimax_search, SearchConfig, Searcher, WIN_SCORE};
+    use crate::rng::Rng;
+    use crate::{HeuristicGameTree, Outcome, Player};
 
     #[test]
     fn new_tic_test() {
@@ -227,6 +319,130 @@ mod tic_tests {
         assert_eq!(print_piece(tic_1.board[6]), " ");
     }
 
+    #[test]
+    fn ordering_tic_test() {
+        let mut tic_1 = TicGame::new();
+        tic_1.store_move(0, Piece::X);
+        let mut ordered = Searcher::new();
+        let mut unordered = Searcher::new().with_config(SearchConfig {
+            move_ordering: false,
+            ..SearchConfig::default()
+        });
+        ordered.search(&tic_1, 8);
+        unordered.search(&tic_1, 8);
+        assert!(ordered.nodes() < unordered.nodes());
+    }
+
+    #[test]
+    fn turn_tic_test() {
+        let mut tic_1 = TicGame::new();
+        assert_eq!(tic_1.to_move(), Player::Min);
+        tic_1.execute_move(&0);
+        assert_eq!(tic_1.board[0], Some(Piece::X));
+        assert_eq!(tic_1.to_move(), Player::Max);
+        tic_1.execute_move(&4);
+        assert_eq!(tic_1.board[4], Some(Piece::O));
+    }
+
+    #[test]
+    fn block_tic_test() {
+        let mut tic_1 = TicGame::new();
+        tic_1.store_move(0, Piece::X);
+        tic_1.store_move(4, Piece::O);
+        tic_1.store_move(1, Piece::X);
+        assert_eq!(minimax_search(&tic_1, 3), Some(2));
+    }
+
+    #[test]
+    fn outcome_tic_test() {
+        let mut tic_1 = TicGame::new();
+        assert_eq!(tic_1.outcome(), Outcome::Ongoing);
+        tic_1.store_move(0, Piece::O);
+        tic_1.store_move(4, Piece::O);
+        tic_1.store_move(8, Piece::O);
+        assert_eq!(tic_1.outcome(), Outcome::Win);
+    }
+
+    #[test]
+    fn fastest_win_tic_test() {
+        let mut tic_1 = TicGame::new();
+        tic_1.store_move(0, Piece::X);
+        tic_1.store_move(3, Piece::O);
+        tic_1.store_move(1, Piece::X);
+        tic_1.store_move(4, Piece::O);
+        tic_1.store_move(8, Piece::X);
+        // O wins on the spot instead of blocking X
+        let result = minimax_analyze(&tic_1, 4);
+        assert_eq!(result.best_move, Some(5));
+        assert_eq!(result.score, WIN_SCORE - 1);
+    }
+
+    #[test]
+    fn book_tic_test() {
+        let book = book_tic(2);
+        let start = TicGame::new();
+        let mut rng = Rng::new(1);
+        assert!(book_move(&book, &start, 3, &mut rng).is_some());
+        // Easy always searches
+        assert_eq!(book_move(&book, &start, 1, &mut rng), None);
+        // Both games made their first move from the start
+        assert_eq!(
+            book.moves(&start)
+                .iter()
+                .map(|(_, weight)| weight)
+                .sum::<u32>(),
+            2
+        );
+    }
+
+    #[test]
+    fn undo_tic_test() {
+        let mut tic_1 = TicGame::new();
+        tic_1.execute_move(&4);
+        let before = tic_1.clone();
+        tic_1.execute_move(&0);
+        tic_1.undo_move(&0);
+        assert_eq!(tic_1.board, before.board);
+        assert_eq!(tic_1.hash, before.hash);
+        assert_eq!(tic_1.to_move(), Player::Max);
+        let mut cloning = Searcher::new().with_config(SearchConfig {
+            undo_moves: false,
+            ..SearchConfig::default()
+        });
+        let undone = Searcher::new().analyze(&tic_1, 6);
+        let cloned = cloning.analyze(&tic_1, 6);
+        assert_eq!(undone.best_move, cloned.best_move);
+        assert_eq!(undone.score, cloned.score);
+    }
+
+    #[test]
+    fn mcts_tic_test() {
+        let mut tic_1 = TicGame::new();
+        tic_1.store_move(0, Piece::X);
+        tic_1.store_move(1, Piece::X);
+        tic_1.store_move(4, Piece::O);
+        assert_eq!(mcts_search(&tic_1, 2000, 11), Some(2));
+        tic_1.store_move(2, Piece::O);
+        tic_1.store_move(8, Piece::X);
+        // O can win at once
+        assert_eq!(mcts_search(&tic_1, 2000, 11), Some(6));
+    }
+
+    #[test]
+    fn hash_tic_test() {
+        let mut tic_1 = TicGame::new();
+        tic_1.store_move(4, Piece::X);
+        tic_1.store_move(0, Piece::O);
+        tic_1.store_move(8, Piece::X);
+        let full = ZOBRIST.hash_pieces(vec![(4, 0), (0, 1), (8, 0)]);
+        assert_eq!(tic_1.hash, full);
+        let mut tic_2 = TicGame::new();
+        tic_2.store_move(8, Piece::X);
+        tic_2.store_move(0, Piece::O);
+        tic_2.store_move(4, Piece::X);
+        assert_eq!(tic_2.hash, tic_1.hash);
+    }
+
     /*#[test]
     fn move_count_test() {
         let mut tic_1 = TicGame::new();
@@ -238,7 +454,7 @@ mod tic_tests {
     fn board_not_full_test() {
         let mut tic_1 = TicGame::new();
         tic_1.store_move(5, Piece::X);
-        assert_eq!(tic_1.board_not_full(), true);
+        assert!(tic_1.board_not_full());
     }
 
     #[test]
@@ -253,7 +469,7 @@ mod tic_tests {
         tic_1.store_move(6, Piece::O);
         tic_1.store_move(7, Piece::X);
         tic_1.store_move(8, Piece::O);
-        assert_eq!(tic_1.board_not_full(), false);
+        assert!(!tic_1.board_not_full());
     }
 
     #[test]
@@ -0,0 +1,103 @@
+use crate::rng::Rng;
+
+/// Random keys for Zobrist hashing of board positions.
+///
+/// Every (square, piece kind) pair gets its own random key, and the hash of a position is the
+/// XOR of the keys of every piece on the board. Because XOR undoes itself, a game can keep its
+/// hash up to date as moves are made: placing or removing a piece is a single XOR with that
+/// piece's key, instead of going over the whole board again.
+///
+/// The keys are computed from a seed when the program is compiled, so hashes are the same from
+/// one run to the next. Games keep their table in a `static`.
+///
+/// # Example
+/// ```
+/// # use heuristic_game_tree::zobrist::ZobristTable;
+/// // A 3x3 board with two kinds of pieces
+/// static ZOBRIST: ZobristTable<9, 2> = ZobristTable::new(9);
+///
+/// let mut hash = ZOBRIST.hash_pieces(vec![(4, 0)]);
+/// hash ^= ZOBRIST.piece(0, 1); // place a piece of kind 1 on square 0
+/// assert_eq!(hash, ZOBRIST.hash_pieces(vec![(4, 0), (0, 1)]));
+/// hash ^= ZOBRIST.piece(0, 1); // and take it off again
+/// assert_eq!(hash, ZOBRIST.hash_pieces(vec![(4, 0)]));
+/// ```
+pub struct ZobristTable<const SQUARES: usize, const KINDS: usize> {
+    pieces: [[u64; KINDS]; SQUARES],
+    side: u64,
+}
+
+impl<const SQUARES: usize, const KINDS: usize> ZobristTable<SQUARES, KINDS> {
+    /// Fills the table with keys drawn from a generator seeded with `seed`.
+    pub const fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut pieces = [[0; KINDS]; SQUARES];
+        let mut square = 0;
+        while square < SQUARES {
+            let mut kind = 0;
+            while kind < KINDS {
+                pieces[square][kind] = rng.next_u64();
+                kind += 1;
+            }
+            square += 1;
+        }
+        ZobristTable {
+            pieces,
+            side: rng.next_u64(),
+        }
+    }
+
+    /// The key of a piece of the given kind on the given square.
+    pub fn piece(&self, square: usize, kind: usize) -> u64 {
+        self.pieces[square][kind]
+    }
+
+    /// The key toggled every time the turn passes to the other player.
+    pub fn side(&self) -> u64 {
+        self.side
+    }
+
+    /// Hashes a whole position from its (square, piece kind) pairs.
+    /// Used to get the first hash of a game, after which it is kept up to date move by move.
+    pub fn hash_pieces<I>(&self, pieces: I) -> u64
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        pieces
+            .into_iter()
+            .fold(0, |hash, (square, kind)| hash ^ self.piece(square, kind))
+    }
+}
+
+#[cfg(test)]
+mod zobrist_tests {
+    use super::ZobristTable;
+
+    static TABLE: ZobristTable<4, 2> = ZobristTable::new(1);
+
+    #[test]
+    fn keys_differ_test() {
+        let mut keys = vec![TABLE.side()];
+        for square in 0..4 {
+            for kind in 0..2 {
+                keys.push(TABLE.piece(square, kind));
+            }
+        }
+        let len = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), len);
+    }
+
+    #[test]
+    fn order_independent_test() {
+        let a = TABLE.hash_pieces(vec![(0, 1), (3, 0)]);
+        let b = TABLE.hash_pieces(vec![(3, 0), (0, 1)]);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn empty_board_test() {
+        assert_eq!(TABLE.hash_pieces(vec![]), 0);
+    }
+}